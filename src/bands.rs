@@ -0,0 +1,32 @@
+use std::thread;
+
+// Découpe `rows`, rangé ligne par ligne avec `row_len` éléments par ligne, en
+// au plus `threads` bandes de lignes consécutives traitées en parallèle.
+// `f` reçoit le numéro de la première ligne de la bande ; les résultats sont
+// renvoyés dans l'ordre des bandes, quel que soit l'ordre d'exécution.
+pub(crate) fn map_bands<T, R, F>(rows: &mut [T], row_len: usize, threads: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(usize, &mut [T]) -> R + Sync,
+{
+    let height = rows.len() / row_len.max(1);
+    let rows_per_band = height.div_ceil(threads.max(1)).max(1);
+    if rows_per_band >= height {
+        return vec![f(0, rows)];
+    }
+    thread::scope(|scope| {
+        let handles: Vec<_> = rows
+            .chunks_mut(rows_per_band * row_len)
+            .enumerate()
+            .map(|(band, rows)| {
+                let f = &f;
+                scope.spawn(move || f(band * rows_per_band, rows))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
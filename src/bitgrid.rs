@@ -0,0 +1,447 @@
+use crate::bands::map_bands;
+use crate::grid::Grid;
+use crate::neighbourhood::Neighbourhood;
+use crate::rule::Rule;
+use crate::tiles::TileMap;
+use crate::topology::Topology;
+
+// Grille à deux états dont chaque ligne est rangée sur des mots de 64 bits :
+// la cellule x est le bit x % 64 du mot x / 64. Les bits au-delà de la
+// largeur sont toujours nuls.
+// La grille est découpée en tuiles d'un mot de large sur TILE_ROWS lignes ;
+// `changed` indique les tuiles modifiées par la dernière génération et
+// `changed_since_previous` celles qui diffèrent de l'avant-dernière, dont les
+// mots sont gardés dans `previous`. `edited` marque les tuiles modifiées à la
+// main depuis la dernière génération.
+#[derive(Debug, Clone)]
+pub struct BitGrid {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+    previous: Vec<u64>,
+    changed: TileMap,
+    changed_since_previous: TileMap,
+    edited: TileMap,
+}
+
+impl BitGrid {
+    const TILE_ROWS: usize = 16;
+
+    // Sans historique, toutes les tuiles sont considérées comme modifiées.
+    pub fn new(width: usize, height: usize) -> BitGrid {
+        let words_per_row = width.div_ceil(64);
+        let tiles = TileMap::new(words_per_row, height.div_ceil(Self::TILE_ROWS), true);
+        BitGrid {
+            width,
+            height,
+            words_per_row,
+            words: vec![0; words_per_row * height],
+            previous: vec![0; words_per_row * height],
+            changed: tiles.clone(),
+            changed_since_previous: tiles.clone(),
+            edited: tiles,
+        }
+    }
+
+    // Renvoie None si la grille contient des cellules mourantes, qui ne
+    // tiennent pas sur un bit.
+    pub fn from_grid(grid: &Grid) -> Option<BitGrid> {
+        let mut bits = BitGrid::new(grid.width(), grid.height());
+        for y in 0..grid.height() {
+            let row = &mut bits.words[y * bits.words_per_row..][..bits.words_per_row];
+            for (word, cells) in row.iter_mut().zip(grid.row(y).chunks(64)) {
+                if cells.iter().any(|cell| cell.state > 1) {
+                    return None;
+                }
+                *word = cells
+                    .iter()
+                    .enumerate()
+                    .fold(0, |word, (x, cell)| word | (cell.state as u64) << x);
+            }
+        }
+        Some(bits)
+    }
+
+    pub fn write_to(&self, grid: &mut Grid) {
+        for y in 0..self.height {
+            let row = &self.words[y * self.words_per_row..][..self.words_per_row];
+            for (&word, cells) in row.iter().zip(grid.row_mut(y).chunks_mut(64)) {
+                for (x, cell) in cells.iter_mut().enumerate() {
+                    cell.state = (word >> x & 1) as u8;
+                }
+            }
+        }
+    }
+
+    // N'écrit que les tuiles modifiées par la dernière génération : la grille
+    // doit correspondre à la génération précédente.
+    pub fn write_changes_to(&self, grid: &mut Grid) {
+        for tile in self.changed.marked() {
+            let (tile_row, i) = (tile / self.words_per_row, tile % self.words_per_row);
+            let first_row = tile_row * Self::TILE_ROWS;
+            for y in first_row..(first_row + Self::TILE_ROWS).min(self.height) {
+                let word = self.words[y * self.words_per_row + i];
+                for (x, cell) in grid.row_mut(y)[64 * i..].iter_mut().take(64).enumerate() {
+                    cell.state = (word >> x & 1) as u8;
+                }
+            }
+        }
+    }
+
+    // Mots changés par la dernière génération : la position de leur première
+    // cellule, le mot d'avant et celui d'après.
+    pub fn changed_words(&self) -> impl Iterator<Item = ((usize, usize), u64, u64)> + '_ {
+        self.changed.marked().flat_map(move |tile| {
+            let (tile_row, i) = (tile / self.words_per_row, tile % self.words_per_row);
+            let first_row = tile_row * Self::TILE_ROWS;
+            (first_row..(first_row + Self::TILE_ROWS).min(self.height)).filter_map(move |y| {
+                let index = y * self.words_per_row + i;
+                let (before, after) = (self.previous[index], self.words[index]);
+                (before != after).then_some(((64 * i, y), before, after))
+            })
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_alive(&self, x: usize, y: usize) -> bool {
+        self.row(y)[x / 64] >> (x % 64) & 1 == 1
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, alive: bool) {
+        let tile = self.changed.index(x / 64, y / Self::TILE_ROWS);
+        self.changed.mark(tile);
+        self.changed_since_previous.mark(tile);
+        self.edited.mark(tile);
+        let word = &mut self.words[y * self.words_per_row + x / 64];
+        if alive {
+            *word |= 1 << (x % 64);
+        } else {
+            *word &= !(1 << (x % 64));
+        }
+    }
+
+    pub fn population(&self) -> u32 {
+        self.words.iter().map(|word| word.count_ones()).sum()
+    }
+
+    fn row(&self, y: usize) -> &[u64] {
+        &self.words[y * self.words_per_row..][..self.words_per_row]
+    }
+
+    // Ligne y (de -1 à height) élargie d'une cellule de chaque côté : la
+    // cellule x est au bit x + 1. Les cellules hors de la grille sont lues à
+    // travers la topologie.
+    fn extended_row(&self, y: isize, topology: Topology, out: &mut [u64]) {
+        out.fill(0);
+        let (w, h) = (self.width, self.height);
+        let alive = |x: isize, y: isize| match topology.resolve(x, y, w, h) {
+            Some((x, y)) => self.is_alive(x, y),
+            None => false,
+        };
+        let set = |out: &mut [u64], bit: usize| out[bit / 64] |= 1 << (bit % 64);
+
+        if (0..h as isize).contains(&y) {
+            let row = self.row(y as usize);
+            let mut carry = 0;
+            for (i, &word) in row.iter().enumerate() {
+                out[i] = word << 1 | carry;
+                carry = word >> 63;
+            }
+            out[row.len()] |= carry;
+            if alive(-1, y) {
+                set(out, 0);
+            }
+            if alive(w as isize, y) {
+                set(out, w + 1);
+            }
+        } else {
+            for x in -1..=w as isize {
+                if alive(x, y) {
+                    set(out, (x + 1) as usize);
+                }
+            }
+        }
+    }
+
+    // Les voisinages personnalisés peuvent aller au-delà de la ligne
+    // élargie d'une cellule.
+    pub fn supports(neighbourhood: Neighbourhood) -> bool {
+        !matches!(neighbourhood, Neighbourhood::Custom(_))
+    }
+
+    // Génération suivante pour une règle totalistique à deux états. Les
+    // voisins de 64 cellules sont additionnés d'un coup, bit par bit, avec
+    // des additionneurs ; le compte obtenu tient sur quatre mots.
+    // Les lignes sont réparties en bandes calculées sur `threads` fils.
+    pub fn step(
+        &self,
+        rule: &Rule,
+        topology: Topology,
+        neighbourhood: Neighbourhood,
+        threads: usize,
+    ) -> BitGrid {
+        assert!(
+            Self::supports(neighbourhood),
+            "voisinage non géré par la grille de bits"
+        );
+        let extended_words = self.words_per_row + 1;
+        let mut extended = vec![0; extended_words * (self.height + 2)];
+        for (i, out) in extended.chunks_mut(extended_words).enumerate() {
+            self.extended_row(i as isize - 1, topology, out);
+        }
+
+        // Pour chaque nombre de voisins, les cellules mortes qui naissent et
+        // les vivantes qui survivent.
+        let outcomes: Vec<(usize, bool, bool)> = (0..=8)
+            .map(|n| (n, rule.born(n), rule.survives(n)))
+            .filter(|&(_, born, survives)| born || survives)
+            .collect();
+        let last_word_mask = match self.width % 64 {
+            0 => !0,
+            bits => (1 << bits) - 1,
+        };
+
+        // Une tuile dont le voisinage n'a pas changé garde son contenu ; si
+        // son voisinage est revenu à l'état d'il y a deux générations, elle
+        // reprend le contenu de la génération précédente.
+        let active = self.changed.neighbourhood();
+        let oscillating = self.changed_since_previous.neighbourhood();
+        let unchanged = TileMap::new(
+            self.words_per_row,
+            self.height.div_ceil(Self::TILE_ROWS),
+            false,
+        );
+        let mut next = BitGrid {
+            width: self.width,
+            height: self.height,
+            words_per_row: self.words_per_row,
+            words: self.words.clone(),
+            previous: self.words.clone(),
+            changed: unchanged.clone(),
+            // Une tuile modifiée à la main ne découle pas de la génération
+            // précédente : elle ne peut pas être reprise à la suivante.
+            changed_since_previous: self.edited.clone(),
+            edited: unchanged,
+        };
+        let changes = map_bands(
+            &mut next.words,
+            self.words_per_row,
+            threads,
+            |first_row, band| {
+                let mut changes = Vec::new();
+                for (dy, row) in band.chunks_mut(self.words_per_row).enumerate() {
+                    let y = first_row + dy;
+                    let above = &extended[y * extended_words..][..extended_words];
+                    let middle = &extended[(y + 1) * extended_words..][..extended_words];
+                    let below = &extended[(y + 2) * extended_words..][..extended_words];
+                    for (i, out) in row.iter_mut().enumerate() {
+                        let tile = self.changed.index(i, y / Self::TILE_ROWS);
+                        if !active.get(tile) {
+                            continue;
+                        }
+                        let previous = self.previous[y * self.words_per_row + i];
+                        if !oscillating.get(tile) {
+                            if previous != *out {
+                                changes.push((tile, true, false));
+                                *out = previous;
+                            }
+                            continue;
+                        }
+                        let bit = 64 * i;
+                        let neighbours = match neighbourhood {
+                            Neighbourhood::VonNeumann => [
+                                window(above, bit + 1),
+                                window(middle, bit),
+                                window(middle, bit + 2),
+                                window(below, bit + 1),
+                                0,
+                                0,
+                                0,
+                                0,
+                            ],
+                            // Une ligne impaire, décalée vers la droite, touche
+                            // les cellules x et x + 1 des lignes voisines ; une
+                            // ligne paire, les cellules x - 1 et x.
+                            Neighbourhood::Hexagonal => {
+                                let shift = y % 2;
+                                [
+                                    window(above, bit + shift),
+                                    window(above, bit + shift + 1),
+                                    window(middle, bit),
+                                    window(middle, bit + 2),
+                                    window(below, bit + shift),
+                                    window(below, bit + shift + 1),
+                                    0,
+                                    0,
+                                ]
+                            }
+                            _ => [
+                                window(above, bit),
+                                window(above, bit + 1),
+                                window(above, bit + 2),
+                                window(middle, bit),
+                                window(middle, bit + 2),
+                                window(below, bit),
+                                window(below, bit + 1),
+                                window(below, bit + 2),
+                            ],
+                        };
+                        let alive = window(middle, bit + 1);
+                        let count = count_bits(neighbours);
+
+                        let mut word = 0;
+                        for &(n, born, survives) in &outcomes {
+                            let matches = (0..4).fold(!0, |acc, j| {
+                                acc & if n >> j & 1 == 1 { count[j] } else { !count[j] }
+                            });
+                            let eligible = match (born, survives) {
+                                (true, true) => !0,
+                                (true, false) => !alive,
+                                _ => alive,
+                            };
+                            word |= matches & eligible;
+                        }
+                        if i == self.words_per_row - 1 {
+                            word &= last_word_mask;
+                        }
+                        if word != *out || word != previous {
+                            changes.push((tile, word != *out, word != previous));
+                            *out = word;
+                        }
+                    }
+                }
+                changes
+            },
+        );
+        for (tile, changed, changed_since_previous) in changes.into_iter().flatten() {
+            if changed {
+                next.changed.mark(tile);
+            }
+            if changed_since_previous {
+                next.changed_since_previous.mark(tile);
+            }
+        }
+        next
+    }
+}
+
+// 64 bits consécutifs de la ligne à partir du bit `bit`.
+fn window(row: &[u64], bit: usize) -> u64 {
+    let (i, shift) = (bit / 64, bit % 64);
+    if shift == 0 {
+        row[i]
+    } else {
+        row[i] >> shift | row[i + 1] << (64 - shift)
+    }
+}
+
+fn full_adder(a: u64, b: u64, c: u64) -> (u64, u64) {
+    (a ^ b ^ c, a & b | c & (a ^ b))
+}
+
+// Somme de huit mots, bit à bit : renvoie les bits de poids 1, 2, 4 et 8.
+fn count_bits([a0, a1, a2, a3, a4, a5, a6, a7]: [u64; 8]) -> [u64; 4] {
+    let (sum_a, carry_a) = full_adder(a0, a1, a2);
+    let (sum_b, carry_b) = full_adder(a3, a4, a5);
+    let (sum_c, carry_c) = (a6 ^ a7, a6 & a7);
+    let (ones, carry_d) = full_adder(sum_a, sum_b, sum_c);
+    let (twos_a, carry_e) = full_adder(carry_a, carry_b, carry_c);
+    let (twos, carry_f) = (twos_a ^ carry_d, twos_a & carry_d);
+    [ones, twos, carry_e ^ carry_f, carry_e & carry_f]
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::grid::Cell;
+
+    // Génération suivante calculée cellule par cellule, sans tuiles ni
+    // mots.
+    fn naive_step(
+        grid: &Grid,
+        rule: &Rule,
+        topology: Topology,
+        neighbourhood: Neighbourhood,
+    ) -> Grid {
+        let (w, h) = (grid.width(), grid.height());
+        let mut next = Grid::new(w, h);
+        for y in 0..h {
+            for x in 0..w {
+                let living = neighbourhood
+                    .offsets(y % 2 == 1)
+                    .iter()
+                    .filter(|&&(dx, dy)| {
+                        topology
+                            .resolve(x as isize + dx, y as isize + dy, w, h)
+                            .is_some_and(|(nx, ny)| grid.is_alive(nx, ny))
+                    })
+                    .count();
+                let alive = grid.is_alive(x, y);
+                if alive && rule.survives(living) || !alive && rule.born(living) {
+                    next.set(x, y, Cell::ALIVE);
+                }
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn step_matches_naive_step() {
+        let rules: Vec<Rule> = ["B3/S23", "B36/S23", "B2/S"]
+            .iter()
+            .map(|rule| rule.parse().unwrap())
+            .collect();
+        let neighbourhoods = [
+            Neighbourhood::Moore,
+            Neighbourhood::VonNeumann,
+            Neighbourhood::Hexagonal,
+        ];
+        let mut rng = StdRng::seed_from_u64(1);
+        for (width, height) in [(70, 37), (130, 20)] {
+            for rule in &rules {
+                for topology in Topology::ALL {
+                    for neighbourhood in neighbourhoods {
+                        let mut expected = Grid::random_with_density(width, height, 0.3, &mut rng);
+                        let mut bits = BitGrid::from_grid(&expected).unwrap();
+                        let mut written = expected.clone();
+                        for generation in 0..12 {
+                            // Une modification à la main ne doit pas être
+                            // effacée par une tuile reprise telle quelle.
+                            if generation % 4 == 3 {
+                                let (x, y) = (rng.gen_range(0..width), rng.gen_range(0..height));
+                                let cell = if expected.is_alive(x, y) {
+                                    Cell::DEAD
+                                } else {
+                                    Cell::ALIVE
+                                };
+                                expected.set(x, y, cell);
+                                written.set(x, y, cell);
+                                bits.set(x, y, cell.is_alive());
+                            }
+                            expected = naive_step(&expected, rule, topology, neighbourhood);
+                            bits = bits.step(rule, topology, neighbourhood, 2);
+                            bits.write_changes_to(&mut written);
+                            let mut full = Grid::new(width, height);
+                            bits.write_to(&mut full);
+                            let case = (rule.to_string(), topology, neighbourhood, generation);
+                            assert_eq!(full, expected, "{:?}", case);
+                            assert_eq!(written, expected, "{:?}", case);
+                            assert_eq!(bits.population(), expected.population(), "{:?}", case);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,459 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+use crate::grid::Cell;
+use crate::neighbourhood::Neighbourhood;
+use crate::rule::Rule;
+use crate::sparse::SparseGrid;
+
+// Nature d'un objet, d'après son évolution seul sur le plan infini. Le
+// déplacement d'un vaisseau est donné pour la direction (dx, dy) avec
+// dx >= dy >= 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObjectKind {
+    StillLife,
+    Oscillator {
+        period: u64,
+    },
+    Spaceship {
+        period: u64,
+        displacement: (i64, i64),
+    },
+    // Ne reprend pas sa forme en MAX_PERIOD générations.
+    Unknown,
+}
+
+impl fmt::Display for ObjectKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ObjectKind::StillLife => write!(f, "nature morte"),
+            ObjectKind::Oscillator { period } => write!(f, "oscillateur de période {}", period),
+            ObjectKind::Spaceship {
+                period,
+                displacement: (dx, dy),
+            } => write!(
+                f,
+                "vaisseau de période {}, déplacement ({}, {})",
+                period, dx, dy
+            ),
+            ObjectKind::Unknown => write!(f, "inconnu"),
+        }
+    }
+}
+
+// Objets connus du jeu de la vie, par code.
+const NAMES: [(&str, &str); 18] = [
+    ("xs4_33", "bloc"),
+    ("xs6_696", "ruche"),
+    ("xs7_2596", "pain"),
+    ("xs5_253", "bateau"),
+    ("xs6_356", "navire"),
+    ("xs4_252", "baignoire"),
+    ("xs8_6996", "mare"),
+    ("xs7_25ac", "long bateau"),
+    ("xs6_25a4", "barge"),
+    ("xs6_bd", "serpent"),
+    ("xp2_7", "clignotant"),
+    ("xp2_7e", "crapaud"),
+    ("xp2_318c", "phare"),
+    ("xp15_4r4z4r4", "pentadécathlon"),
+    ("xq4_153", "planeur"),
+    ("xq4_6frc", "vaisseau léger"),
+    ("xq4_27dee6", "vaisseau moyen"),
+    ("xq4_27deee6", "vaisseau lourd"),
+];
+
+// Une sorte d'objet et le nombre d'exemplaires trouvés. Le code est celui
+// de Catagolue (apgcode) : xs suivi de la population pour une nature
+// morte, xp ou xq suivi de la période pour un oscillateur ou un vaisseau,
+// puis la forme en format de Wechsler étendu, la plus courte parmi toutes
+// les phases et orientations. Les objets inconnus sont regroupés.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CensusEntry {
+    pub code: String,
+    pub name: Option<&'static str>,
+    pub kind: ObjectKind,
+    pub count: u32,
+}
+
+// Objets d'une génération, les plus nombreux d'abord. Un objet est un
+// ensemble de cellules vivantes reliées par leurs coins ou leurs côtés,
+// dans la génération ou dans la suivante, ce qui garde ensemble les
+// parties d'un oscillateur comme le phare.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Census {
+    entries: Vec<CensusEntry>,
+}
+
+impl Census {
+    // Au-delà, un objet qui n'a pas repris sa forme est inconnu.
+    pub const MAX_PERIOD: u64 = 240;
+    // Un objet qui dépasse cette population en évoluant est inconnu.
+    const MAX_POPULATION: usize = 10_000;
+    const UNKNOWN: &'static str = "inconnu";
+
+    pub fn entries(&self) -> &[CensusEntry] {
+        &self.entries
+    }
+
+    pub fn total(&self) -> u32 {
+        self.entries.iter().map(|entry| entry.count).sum()
+    }
+
+    // `next` est la génération suivante ; `neighbour` déplace une cellule
+    // d'un décalage (dx, dy) selon la topologie, None hors du monde.
+    pub(crate) fn take(
+        current: &[(i64, i64)],
+        next: &[(i64, i64)],
+        neighbour: impl Fn((i64, i64), (i64, i64)) -> Option<(i64, i64)>,
+        rule: &Rule,
+        neighbourhood: Neighbourhood,
+    ) -> Census {
+        let mut census = Census::default();
+        // Les morceaux inconnus proches les uns des autres peuvent former
+        // ensemble un objet connu, comme les vaisseaux dont les cellules ne se
+        // touchent pas.
+        let mut fragments = Vec::new();
+        for object in components(current, next, 1, &neighbour) {
+            let local: Vec<(i64, i64)> = object.iter().map(|&(_, local)| local).collect();
+            match classify(&local, rule, neighbourhood) {
+                (ObjectKind::Unknown, _) => fragments.extend(object.iter().map(|&(cell, _)| cell)),
+                (kind, code) => census.add(kind, code, rule, neighbourhood),
+            }
+        }
+        for object in components(&fragments, &[], 2, &neighbour) {
+            let local: Vec<(i64, i64)> = object.iter().map(|&(_, local)| local).collect();
+            let (kind, code) = classify(&local, rule, neighbourhood);
+            census.add(kind, code, rule, neighbourhood);
+        }
+        census.sort();
+        census
+    }
+
+    // Ajoute les objets de `other`, pour cumuler les recensements de
+    // plusieurs parties.
+    pub fn merge(&mut self, other: &Census) {
+        for entry in &other.entries {
+            match self.entries.iter_mut().find(|own| own.code == entry.code) {
+                Some(own) => own.count += entry.count,
+                None => self.entries.push(entry.clone()),
+            }
+        }
+        self.sort();
+    }
+
+    fn add(&mut self, kind: ObjectKind, code: String, rule: &Rule, neighbourhood: Neighbourhood) {
+        match self.entries.iter_mut().find(|entry| entry.code == code) {
+            Some(entry) => entry.count += 1,
+            None => {
+                let known = *rule == Rule::CONWAY && neighbourhood == Neighbourhood::Moore;
+                let name = NAMES
+                    .iter()
+                    .find(|&&(known_code, _)| known && known_code == code)
+                    .map(|&(_, name)| name);
+                self.entries.push(CensusEntry {
+                    code,
+                    name,
+                    kind,
+                    count: 1,
+                });
+            }
+        }
+    }
+
+    fn sort(&mut self) {
+        self.entries
+            .sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.code.cmp(&b.code)));
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("code,nom,nature,nombre\n");
+        for entry in &self.entries {
+            csv.push_str(&format!(
+                "{},{},\"{}\",{}\n",
+                entry.code,
+                entry.name.unwrap_or(""),
+                entry.kind,
+                entry.count
+            ));
+        }
+        csv
+    }
+}
+
+type Position = (i64, i64);
+
+// Cellules de `current` regroupées par objet, deux cellules à moins de
+// `reach` l'une de l'autre étant dans le même objet. Chacune est donnée avec
+// sa position repérée par rapport à la première cellule de son objet
+// trouvée, pour qu'un objet à cheval sur les bords d'un tore reste d'un
+// seul tenant.
+fn components(
+    current: &[(i64, i64)],
+    next: &[(i64, i64)],
+    reach: i64,
+    neighbour: impl Fn((i64, i64), (i64, i64)) -> Option<(i64, i64)>,
+) -> Vec<Vec<(Position, Position)>> {
+    let living: HashSet<(i64, i64)> = current.iter().copied().collect();
+    let linked: HashSet<(i64, i64)> = current.iter().chain(next).copied().collect();
+    let mut seen = HashSet::new();
+    let mut objects = Vec::new();
+    for &start in current {
+        if !seen.insert(start) {
+            continue;
+        }
+        let mut object = Vec::new();
+        let mut queue = VecDeque::from([(start, start)]);
+        while let Some((cell, (x, y))) = queue.pop_front() {
+            if living.contains(&cell) {
+                object.push((cell, (x, y)));
+            }
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    let Some(other) = neighbour(cell, (dx, dy)) else {
+                        continue;
+                    };
+                    if linked.contains(&other) && seen.insert(other) {
+                        queue.push_back((other, (x + dx, y + dy)));
+                    }
+                }
+            }
+        }
+        objects.push(object);
+    }
+    objects
+}
+
+// Fait évoluer l'objet seul sur le plan infini jusqu'à ce qu'il reprenne
+// sa forme, à une translation près.
+pub(crate) fn classify(
+    object: &[(i64, i64)],
+    rule: &Rule,
+    neighbourhood: Neighbourhood,
+) -> (ObjectKind, String) {
+    let unknown = (ObjectKind::Unknown, Census::UNKNOWN.to_string());
+    let mut plane = SparseGrid::new();
+    for &(x, y) in object {
+        plane.set(x, y, Cell { state: 1 });
+    }
+    let (start, (x0, y0)) = normalize(&plane);
+    let mut phases = vec![start.clone()];
+    for generation in 1..=Census::MAX_PERIOD {
+        plane = plane.step(rule, neighbourhood);
+        let (shape, (x, y)) = normalize(&plane);
+        if shape.is_empty() || shape.len() > Census::MAX_POPULATION {
+            return unknown;
+        }
+        if shape == start {
+            let (dx, dy) = ((x - x0).abs(), (y - y0).abs());
+            let (kind, prefix) = match (generation, (dx, dy)) {
+                (1, (0, 0)) => (ObjectKind::StillLife, format!("xs{}", start.len())),
+                (period, (0, 0)) => (ObjectKind::Oscillator { period }, format!("xp{}", period)),
+                (period, _) => (
+                    ObjectKind::Spaceship {
+                        period,
+                        displacement: (dx.max(dy), dx.min(dy)),
+                    },
+                    format!("xq{}", period),
+                ),
+            };
+            return (kind, format!("{}_{}", prefix, shortest_code(&phases)));
+        }
+        phases.push(shape);
+    }
+    unknown
+}
+
+// Cellules vivantes triées, repérées par rapport au coin supérieur gauche
+// de leur rectangle, et la position de ce coin.
+fn normalize(plane: &SparseGrid) -> (Vec<(i64, i64)>, (i64, i64)) {
+    let Some((x_min, y_min, _, _)) = plane.bounding_box() else {
+        return (Vec::new(), (0, 0));
+    };
+    let mut cells: Vec<(i64, i64)> = plane
+        .cells()
+        .filter(|(_, cell)| cell.is_alive())
+        .map(|((x, y), _)| (x - x_min, y - y_min))
+        .collect();
+    cells.sort_unstable();
+    (cells, (x_min, y_min))
+}
+
+// Le plus court des codes de Wechsler des phases dans leurs huit
+// orientations, le premier dans l'ordre alphabétique à longueur égale.
+fn shortest_code(phases: &[Vec<(i64, i64)>]) -> String {
+    let mut best: Option<String> = None;
+    for phase in phases {
+        for symmetry in 0..8 {
+            let code = wechsler(&orient(phase, symmetry));
+            let shorter = best
+                .as_ref()
+                .is_none_or(|best| (code.len(), &code) < (best.len(), best));
+            if shorter {
+                best = Some(code);
+            }
+        }
+    }
+    best.unwrap_or_default()
+}
+
+// Une des huit symétries du carré, les cellules ramenées en (0, 0).
+fn orient(cells: &[(i64, i64)], symmetry: u8) -> Vec<(i64, i64)> {
+    let oriented: Vec<(i64, i64)> = cells
+        .iter()
+        .map(|&(x, y)| {
+            let (x, y) = if symmetry & 4 != 0 { (y, x) } else { (x, y) };
+            (
+                if symmetry & 1 != 0 { -x } else { x },
+                if symmetry & 2 != 0 { -y } else { y },
+            )
+        })
+        .collect();
+    let x_min = oriented.iter().map(|&(x, _)| x).min().unwrap_or(0);
+    let y_min = oriented.iter().map(|&(_, y)| y).min().unwrap_or(0);
+    oriented
+        .into_iter()
+        .map(|(x, y)| (x - x_min, y - y_min))
+        .collect()
+}
+
+// Format de Wechsler étendu : les lignes sont prises par bandes de cinq,
+// séparées par z ; chaque colonne d'une bande est un chiffre en base 32 dont
+// le bit i est la cellule de la i-ème ligne. Les suites de colonnes vides
+// s'écrivent w (2), x (3) ou y suivi du nombre moins 4 en base 36, celles
+// en fin de bande sont omises.
+fn wechsler(cells: &[(i64, i64)]) -> String {
+    const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let living: HashSet<(i64, i64)> = cells.iter().copied().collect();
+    let width = cells.iter().map(|&(x, _)| x + 1).max().unwrap_or(0);
+    let height = cells.iter().map(|&(_, y)| y + 1).max().unwrap_or(0);
+    let mut strips = Vec::new();
+    for top in (0..height).step_by(5) {
+        let mut strip = String::new();
+        let mut blanks = 0;
+        for x in 0..width {
+            let column = (0..5)
+                .filter(|&dy| living.contains(&(x, top + dy)))
+                .fold(0, |column, dy| column | 1 << dy);
+            if column == 0 {
+                blanks += 1;
+                continue;
+            }
+            while blanks > 0 {
+                let run = blanks.min(39);
+                match run {
+                    1 => strip.push('0'),
+                    2 => strip.push('w'),
+                    3 => strip.push('x'),
+                    _ => {
+                        strip.push('y');
+                        strip.push(DIGITS[run - 4] as char);
+                    }
+                }
+                blanks -= run;
+            }
+            strip.push(DIGITS[column] as char);
+        }
+        strips.push(strip);
+    }
+    strips.join("z")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cellules vivantes d'un dessin, une chaîne par ligne, `o` pour vivante.
+    fn cells(rows: &[&str]) -> Vec<(i64, i64)> {
+        let mut cells = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == 'o' {
+                    cells.push((x as i64, y as i64));
+                }
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn known_objects_get_their_apgcode() {
+        let objects = [
+            (cells(&["oo", "oo"]), "xs4_33", ObjectKind::StillLife),
+            (
+                cells(&[".o.", "o.o", "o.o", ".o."]),
+                "xs6_696",
+                ObjectKind::StillLife,
+            ),
+            (
+                cells(&["ooo"]),
+                "xp2_7",
+                ObjectKind::Oscillator { period: 2 },
+            ),
+            (
+                cells(&["o", "o", "o"]),
+                "xp2_7",
+                ObjectKind::Oscillator { period: 2 },
+            ),
+            (
+                cells(&[".o.", "..o", "ooo"]),
+                "xq4_153",
+                ObjectKind::Spaceship {
+                    period: 4,
+                    displacement: (1, 1),
+                },
+            ),
+            (
+                cells(&["o..o.", "....o", "o...o", ".oooo"]),
+                "xq4_6frc",
+                ObjectKind::Spaceship {
+                    period: 4,
+                    displacement: (2, 0),
+                },
+            ),
+        ];
+        for (object, code, kind) in objects {
+            assert_eq!(
+                classify(&object, &Rule::CONWAY, Neighbourhood::Moore),
+                (kind, code.to_string()),
+                "{:?}",
+                object
+            );
+        }
+    }
+
+    #[test]
+    fn census_counts_separate_objects() {
+        let current = cells(&[
+            "oo.....ooo",
+            "oo........",
+            "..........",
+            "..........",
+            ".o.....oo.",
+            "..o....oo.",
+            "ooo.......",
+        ]);
+        let next: Vec<(i64, i64)> = {
+            let mut plane = SparseGrid::new();
+            for &(x, y) in &current {
+                plane.set(x, y, Cell { state: 1 });
+            }
+            let plane = plane.step(&Rule::CONWAY, Neighbourhood::Moore);
+            plane.cells().map(|(cell, _)| cell).collect()
+        };
+        let census = Census::take(
+            &current,
+            &next,
+            |(x, y), (dx, dy)| Some((x + dx, y + dy)),
+            &Rule::CONWAY,
+            Neighbourhood::Moore,
+        );
+        let counts: Vec<(&str, u32)> = census
+            .entries()
+            .iter()
+            .map(|entry| (entry.code.as_str(), entry.count))
+            .collect();
+        assert_eq!(counts, [("xs4_33", 2), ("xp2_7", 1), ("xq4_153", 1)]);
+        assert_eq!(census.entries()[0].name, Some("bloc"));
+    }
+}
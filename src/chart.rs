@@ -0,0 +1,160 @@
+use iced::mouse;
+use iced::widget::canvas::{self, Frame, Geometry, Path, Stroke, Text};
+use iced::{color, Color, Point, Rectangle, Renderer, Size, Theme};
+
+use conway::Simulation;
+
+use crate::Message;
+
+// Population, naissances et morts d'une génération.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    generation: u64,
+    population: u32,
+    births: u32,
+    deaths: u32,
+}
+
+// Suite des mesures d'une partie, une par génération calculée (ou par saut),
+// limitée aux MAX_SAMPLES dernières.
+#[derive(Debug, Clone, Default)]
+pub struct Series {
+    samples: Vec<Sample>,
+}
+
+impl Series {
+    const MAX_SAMPLES: usize = 100_000;
+
+    // Après une annulation, les mesures des générations annulées sont
+    // remplacées par les nouvelles.
+    pub fn record(&mut self, simulation: &Simulation) {
+        let generation = simulation.generation();
+        self.forget_after(generation.saturating_sub(1));
+        let (births, deaths) = simulation.births_and_deaths().unwrap_or((0, 0));
+        self.samples.push(Sample {
+            generation,
+            population: simulation.population(),
+            births,
+            deaths,
+        });
+        if self.samples.len() > Self::MAX_SAMPLES {
+            self.samples.drain(..self.samples.len() - Self::MAX_SAMPLES);
+        }
+    }
+
+    // Oublie les mesures des générations postérieures à `generation`.
+    pub fn forget_after(&mut self, generation: u64) {
+        let kept = self
+            .samples
+            .partition_point(|sample| sample.generation <= generation);
+        self.samples.truncate(kept);
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("generation,population,naissances,morts\n");
+        for sample in &self.samples {
+            csv.push_str(&format!(
+                "{},{},{},{}\n",
+                sample.generation, sample.population, sample.births, sample.deaths
+            ));
+        }
+        csv
+    }
+}
+
+// Nom, valeur mesurée et couleur d'une courbe.
+type Curve = (&'static str, fn(&Sample) -> u32, Color);
+
+// Courbes des `window` dernières générations (toutes si None) : population
+// en noir, naissances en vert, morts en rouge, sur une même échelle.
+pub struct Chart<'a> {
+    pub series: &'a Series,
+    pub window: Option<u64>,
+}
+
+// Largeurs de fenêtre proposées, en générations.
+pub const WINDOWS: [Option<u64>; 4] = [Some(100), Some(1000), Some(10_000), None];
+
+impl canvas::Program<Message> for Chart<'_> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        frame.fill_rectangle(Point::ORIGIN, bounds.size(), Color::WHITE);
+        let samples = &self.series.samples;
+        let Some(last) = samples.last() else {
+            return vec![frame.into_geometry()];
+        };
+        let first_generation = match self.window {
+            Some(window) => last.generation.saturating_sub(window),
+            None => samples[0].generation,
+        };
+        let shown = &samples[samples.partition_point(|s| s.generation < first_generation)..];
+        let highest = shown
+            .iter()
+            .map(|s| s.population.max(s.births).max(s.deaths))
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        // Une marge à gauche pour l'échelle.
+        let margin = 50.0;
+        let plot = Size::new(bounds.width - margin, bounds.height - 10.0);
+        let span = (last.generation - first_generation).max(1) as f32;
+        let point = |generation: u64, value: u32| {
+            Point::new(
+                margin + (generation - first_generation) as f32 / span * plot.width,
+                5.0 + plot.height * (1.0 - value as f32 / highest as f32),
+            )
+        };
+        let axes = Path::new(|path| {
+            path.move_to(Point::new(margin, 5.0));
+            path.line_to(Point::new(margin, 5.0 + plot.height));
+            path.line_to(Point::new(bounds.width, 5.0 + plot.height));
+        });
+        frame.stroke(&axes, Stroke::default().with_color(color!(0xBFBFBF)));
+        frame.fill_text(Text {
+            content: highest.to_string(),
+            position: Point::new(2.0, 2.0),
+            ..Text::default()
+        });
+        frame.fill_text(Text {
+            content: first_generation.to_string(),
+            position: Point::new(2.0, plot.height - 12.0),
+            ..Text::default()
+        });
+
+        let curves: [Curve; 3] = [
+            ("population", |s| s.population, Color::BLACK),
+            ("naissances", |s| s.births, color!(0x20A040)),
+            ("morts", |s| s.deaths, color!(0xD03020)),
+        ];
+        for (i, (name, value, colour)) in curves.into_iter().enumerate() {
+            frame.fill_text(Text {
+                content: name.to_string(),
+                position: Point::new(bounds.width - 90.0, 5.0 + 16.0 * i as f32),
+                color: colour,
+                ..Text::default()
+            });
+            let curve = Path::new(|path| {
+                for (i, sample) in shown.iter().enumerate() {
+                    let p = point(sample.generation, value(sample));
+                    if i == 0 {
+                        path.move_to(p);
+                    } else {
+                        path.line_to(p);
+                    }
+                }
+            });
+            frame.stroke(&curve, Stroke::default().with_color(colour).with_width(1.5));
+        }
+        vec![frame.into_geometry()]
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::grid::Cell;
+
+// Ce qu'est devenue une partie dont une génération en répète une autre.
+// Sur le plan infini, les générations sont comparées à une translation près :
+// un vaisseau a une période et un déplacement par période.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cycle {
+    Extinct {
+        since: u64,
+    },
+    Stable {
+        since: u64,
+    },
+    Periodic {
+        period: u64,
+        since: u64,
+        displacement: (i64, i64),
+    },
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Cycle::Extinct { since } => write!(f, "éteinte depuis la génération {}", since),
+            Cycle::Stable { since } => write!(f, "stable depuis la génération {}", since),
+            Cycle::Periodic {
+                period,
+                since,
+                displacement: (0, 0),
+            } => write!(f, "période {} depuis la génération {}", period, since),
+            Cycle::Periodic {
+                period,
+                since,
+                displacement: (dx, dy),
+            } => write!(
+                f,
+                "période {} depuis la génération {}, déplacement ({}, {})",
+                period, since, dx, dy
+            ),
+        }
+    }
+}
+
+// Empreinte d'une génération : un hachage de ses cellules, la position de
+// son coin supérieur gauche sur le plan infini, (0, 0) ailleurs, et si
+// toutes ses cellules sont mortes.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fingerprint {
+    hash: u64,
+    offset: (i64, i64),
+    empty: bool,
+}
+
+impl Fingerprint {
+    pub(crate) fn new(words: impl Iterator<Item = u64>, offset: (i64, i64)) -> Fingerprint {
+        let mut hash = 0u64;
+        let mut empty = true;
+        for word in words {
+            empty &= word == 0;
+            hash = (hash.rotate_left(5) ^ word).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+        }
+        Fingerprint {
+            hash: hash ^ hash >> 29,
+            offset,
+            empty,
+        }
+    }
+
+    // Empreinte d'une grille bornée à partir de son hachage par blocs : une
+    // grille non vide qui hacherait à 0 serait aussi improbable qu'une
+    // collision entre deux générations.
+    pub(crate) fn from_hash(hash: u64) -> Fingerprint {
+        Fingerprint {
+            hash,
+            offset: (0, 0),
+            empty: hash == 0,
+        }
+    }
+}
+
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ z >> 30).wrapping_mul(0xbf_58_47_6d_1c_e4_e5_b9);
+    z = (z ^ z >> 27).wrapping_mul(0x94_d0_49_bb_13_31_11_eb);
+    z ^ z >> 31
+}
+
+// Hachage d'un bloc de 64 cellules d'une ligne commençant en (x0, y), décrit
+// par les plans de bits de leurs états : le bit x - x0 du plan k est le bit
+// k de l'état de la cellule x. Le hachage d'une grille bornée est le ou
+// exclusif de ceux de ses blocs, et se tient donc à jour bloc par bloc ; il
+// vaut 0 pour une grille vide.
+pub(crate) fn block_hash(x0: usize, y: usize, planes: &[u64]) -> u64 {
+    planes
+        .iter()
+        .enumerate()
+        .filter(|&(_, &plane)| plane != 0)
+        .fold(0, |hash, (k, &plane)| {
+            hash ^ mix((y as u64) << 32 ^ (x0 as u64) << 3 ^ k as u64 ^ mix(plane))
+        })
+}
+
+// Hachage d'un bloc d'au plus 64 cellules commençant en (x0, y).
+pub(crate) fn cells_block_hash(x0: usize, y: usize, cells: &[Cell]) -> u64 {
+    let mut planes = [0u64; 8];
+    for (bit, cell) in cells.iter().enumerate() {
+        for (k, plane) in planes.iter_mut().enumerate() {
+            *plane |= ((cell.state >> k & 1) as u64) << bit;
+        }
+    }
+    block_hash(x0, y, &planes)
+}
+
+// Génération et position de chaque empreinte déjà vue, jusqu'à ce qu'une
+// génération en répète une autre. Au-delà de MAX_REMEMBERED empreintes, les
+// plus anciennes sont oubliées et les très longues périodes passent
+// inaperçues.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CycleDetector {
+    seen: HashMap<u64, (u64, (i64, i64))>,
+    found: Option<Cycle>,
+}
+
+impl CycleDetector {
+    const MAX_REMEMBERED: usize = 1 << 20;
+
+    pub(crate) fn found(&self) -> Option<Cycle> {
+        self.found
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.seen.is_empty() && self.found.is_none()
+    }
+
+    pub(crate) fn observe(&mut self, generation: u64, fingerprint: Fingerprint) {
+        if self.found.is_some() {
+            return;
+        }
+        if fingerprint.empty {
+            self.found = Some(Cycle::Extinct { since: generation });
+            return;
+        }
+        if let Some(&(since, (x, y))) = self.seen.get(&fingerprint.hash) {
+            let (x1, y1) = fingerprint.offset;
+            self.found = Some(match (generation - since, (x1 - x, y1 - y)) {
+                (1, (0, 0)) => Cycle::Stable { since },
+                (period, displacement) => Cycle::Periodic {
+                    period,
+                    since,
+                    displacement,
+                },
+            });
+            return;
+        }
+        if self.seen.len() >= Self::MAX_REMEMBERED {
+            self.seen.clear();
+        }
+        self.seen
+            .insert(fingerprint.hash, (generation, fingerprint.offset));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::grid::Grid;
+    use crate::neighbourhood::Neighbourhood;
+    use crate::rule::Rule;
+    use crate::simulation::Simulation;
+    use crate::topology::Topology;
+
+    // Hachage de la grille recalculé bloc par bloc.
+    fn fresh_hash(grid: &Grid) -> u64 {
+        let mut hash = 0;
+        for y in 0..grid.height() {
+            for x0 in (0..grid.width()).step_by(64) {
+                let row = &grid.row(y)[x0..];
+                hash ^= cells_block_hash(x0, y, &row[..row.len().min(64)]);
+            }
+        }
+        hash
+    }
+
+    #[test]
+    fn kept_hash_matches_a_fresh_one() {
+        let mut rng = StdRng::seed_from_u64(2);
+        for rule in ["B3/S23", "B2/S/C3", "B3/S2-i34q"] {
+            let rule: Rule = rule.parse().unwrap();
+            for topology in Topology::ALL {
+                for neighbourhood in [Neighbourhood::Moore, Neighbourhood::Hexagonal] {
+                    if topology == Topology::Unbounded || !neighbourhood.supports(&rule) {
+                        continue;
+                    }
+                    let grid = Grid::random_with_density(150, 20, 0.3, &mut rng);
+                    let mut simulation = Simulation::with_rule(grid, rule);
+                    simulation.set_topology(topology);
+                    simulation.set_neighbourhood(neighbourhood);
+                    let dying = Cell {
+                        state: rule.states() - 1,
+                    };
+                    for action in 0..40 {
+                        let (x, y) = (rng.gen_range(0..150), rng.gen_range(0..20));
+                        match action % 8 {
+                            1 => simulation.toggle(x, y),
+                            3 => simulation.paint(x, y, dying),
+                            5 => simulation.undo(),
+                            6 => simulation.jump(1),
+                            7 if action == 15 => {
+                                simulation.replace_cells(&[(x as i64, y as i64), (70, 3)])
+                            }
+                            _ => simulation.step(),
+                        }
+                        let case = (rule.to_string(), topology, neighbourhood, action);
+                        assert_eq!(
+                            simulation.fingerprint().hash,
+                            fresh_hash(simulation.grid()),
+                            "{:?}",
+                            case
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn cycle_of(cells: &[(i64, i64)]) -> Option<Cycle> {
+        let mut simulation = Simulation::new(Grid::new(20, 20));
+        simulation.set_topology(Topology::Unbounded);
+        simulation.replace_cells(cells);
+        simulation.run(8);
+        simulation.cycle()
+    }
+
+    #[test]
+    fn known_patterns_are_recognised() {
+        let blinker = [(0, 1), (1, 1), (2, 1)];
+        let block = [(0, 0), (1, 0), (0, 1), (1, 1)];
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        assert_eq!(
+            cycle_of(&blinker),
+            Some(Cycle::Periodic {
+                period: 2,
+                since: 1,
+                displacement: (0, 0)
+            })
+        );
+        assert_eq!(cycle_of(&block), Some(Cycle::Stable { since: 1 }));
+        assert_eq!(cycle_of(&[(5, 5)]), Some(Cycle::Extinct { since: 2 }));
+        assert_eq!(
+            cycle_of(&glider),
+            Some(Cycle::Periodic {
+                period: 4,
+                since: 1,
+                displacement: (1, 1)
+            })
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use crate::grid::Cell;
+use crate::rule::Elementary;
+use crate::topology::Topology;
+
+// Ligne suivante d'un automate à une dimension. Les cellules au-delà des
+// bords sont lues à travers la topologie, comme sur une grille d'une seule
+// ligne.
+pub(crate) fn next_row(row: &[Cell], rule: Elementary, topology: Topology) -> Vec<Cell> {
+    let width = row.len();
+    let radius = rule.radius as isize;
+    let alive = |x: isize| match topology.resolve(x, 0, width, 1) {
+        Some((x, _)) => row[x].is_alive(),
+        None => false,
+    };
+    (0..width as isize)
+        .map(|x| {
+            let cells = (x - radius..=x + radius).map(alive);
+            let index = if rule.totalistic {
+                cells.filter(|&alive| alive).count() as u32
+            } else {
+                cells.fold(0, |index, alive| index << 1 | alive as u32)
+            };
+            if rule.code >> index & 1 == 1 {
+                Cell::ALIVE
+            } else {
+                Cell::DEAD
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,289 @@
+use std::fmt;
+
+use rand::Rng;
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// Etat d'une cellule : 0 pour une cellule morte, 1 pour une cellule vivante.
+// Avec les règles "Generations", les états suivants sont ceux d'une cellule
+// en train de mourir.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Cell {
+    pub state: u8,
+}
+
+impl Cell {
+    pub const DEAD: Cell = Cell { state: 0 };
+    pub const ALIVE: Cell = Cell { state: 1 };
+
+    pub fn is_alive(&self) -> bool {
+        self.state == 1
+    }
+}
+
+// Grille de width x height cellules, rangées ligne par ligne.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+impl Grid {
+    pub const DEFAULT_SIZE: usize = 50;
+    pub const MIN_SIZE: usize = 8;
+    pub const MAX_SIZE: usize = 4096;
+
+    pub fn new(width: usize, height: usize) -> Grid {
+        Grid {
+            width,
+            height,
+            cells: vec![Cell::default(); width * height],
+        }
+    }
+
+    pub fn is_valid_size(size: usize) -> bool {
+        (Self::MIN_SIZE..=Self::MAX_SIZE).contains(&size)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Cell {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn is_alive(&self, x: usize, y: usize) -> bool {
+        self.get(x, y).is_alive()
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        self.cells[y * self.width + x] = cell;
+    }
+
+    pub(crate) fn row(&self, y: usize) -> &[Cell] {
+        &self.cells[y * self.width..][..self.width]
+    }
+
+    pub(crate) fn cells_mut(&mut self) -> &mut [Cell] {
+        &mut self.cells
+    }
+
+    pub(crate) fn row_mut(&mut self, y: usize) -> &mut [Cell] {
+        &mut self.cells[y * self.width..][..self.width]
+    }
+
+    pub fn population(&self) -> u32 {
+        self.cells.iter().filter(|cell| cell.is_alive()).count() as u32
+    }
+
+    // Chaque cellule est vivante avec la probabilité `density` (entre 0 et 1).
+    pub fn random_with_density<R: Rng>(
+        width: usize,
+        height: usize,
+        density: f64,
+        rng: &mut R,
+    ) -> Grid {
+        let mut grid = Grid::new(width, height);
+        for cell in grid.cells.iter_mut() {
+            if rng.gen_bool(density) {
+                *cell = Cell::ALIVE;
+            }
+        }
+        grid
+    }
+
+    // Place exactement `number_of_cells` cellules vivantes (au plus width * height).
+    pub fn random_with_number_of_cells<R: Rng>(
+        width: usize,
+        height: usize,
+        number_of_cells: u32,
+        rng: &mut R,
+    ) -> Grid {
+        let mut grid = Grid::new(width, height);
+        let number_of_cells = number_of_cells.min(grid.cells.len() as u32);
+        let mut count_cells = 0;
+        while count_cells < number_of_cells {
+            let x = rng.gen_range(0..width);
+            let y = rng.gen_range(0..height);
+            if !grid.is_alive(x, y) {
+                grid.set(x, y, Cell::ALIVE);
+                count_cells += 1;
+            }
+        }
+        grid
+    }
+
+    // Lit un motif au format texte "plaintext" (.cells) : 'O' ou '*' pour une
+    // cellule vivante, '.' pour une cellule morte, '!' en début de ligne pour
+    // un commentaire. Le motif est placé dans le coin supérieur gauche.
+    pub fn from_plaintext(pattern: &str, width: usize, height: usize) -> Result<Grid, String> {
+        let mut grid = Grid::new(width, height);
+        let lines = pattern.lines().filter(|line| !line.starts_with('!'));
+        for (y, line) in lines.enumerate() {
+            for (x, c) in line.trim_end().chars().enumerate() {
+                let cell = match c {
+                    'O' | '*' => Cell::ALIVE,
+                    '.' => Cell::DEAD,
+                    _ => return Err(format!("Caractère inattendu '{}' ligne {}", c, y + 1)),
+                };
+                if x >= width || y >= height {
+                    if cell.is_alive() {
+                        return Err(format!(
+                            "Le motif dépasse la grille de {}x{}",
+                            width, height
+                        ));
+                    }
+                    continue;
+                }
+                grid.set(x, y, cell);
+            }
+        }
+        Ok(grid)
+    }
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Grid::new(Self::DEFAULT_SIZE, Self::DEFAULT_SIZE)
+    }
+}
+
+// Une cellule est sauvegardée sous la forme de son état. Les anciennes
+// sauvegardes utilisent un objet {"living": bool}.
+impl Serialize for Cell {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u8(self.state)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cell {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CellVisitor;
+
+        impl<'de> Visitor<'de> for CellVisitor {
+            type Value = Cell;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "a cell state or a {{\"living\": bool}} object")
+            }
+
+            fn visit_u64<E>(self, state: u64) -> Result<Cell, E>
+            where
+                E: de::Error,
+            {
+                u8::try_from(state)
+                    .map(|state| Cell { state })
+                    .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(state), &self))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Cell, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut cell = Cell::DEAD;
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == "living" {
+                        if map.next_value()? {
+                            cell = Cell::ALIVE;
+                        }
+                    } else {
+                        map.next_value::<de::IgnoredAny>()?;
+                    }
+                }
+                Ok(cell)
+            }
+        }
+
+        deserializer.deserialize_any(CellVisitor)
+    }
+}
+
+#[derive(Serialize)]
+struct GridRef<'a> {
+    width: usize,
+    height: usize,
+    cells: &'a [Cell],
+}
+
+// Les anciennes sauvegardes ne contiennent que la liste des cellules d'une
+// grille carrée, rangées colonne par colonne.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GridRepr {
+    Sized {
+        width: usize,
+        height: usize,
+        cells: Vec<Cell>,
+    },
+    Legacy(Vec<Cell>),
+}
+
+impl Serialize for Grid {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        GridRef {
+            width: self.width,
+            height: self.height,
+            cells: &self.cells,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Grid {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match GridRepr::deserialize(deserializer)? {
+            GridRepr::Sized {
+                width,
+                height,
+                cells,
+            } => {
+                if cells.len() != width * height {
+                    return Err(serde::de::Error::custom(format!(
+                        "expected {} cells for a {}x{} grid, found {}",
+                        width * height,
+                        width,
+                        height,
+                        cells.len()
+                    )));
+                }
+                Ok(Grid {
+                    width,
+                    height,
+                    cells,
+                })
+            }
+            GridRepr::Legacy(cells) => {
+                let size = (cells.len() as f64).sqrt() as usize;
+                if size * size != cells.len() {
+                    return Err(serde::de::Error::custom(format!(
+                        "expected a square grid, found {} cells",
+                        cells.len()
+                    )));
+                }
+                let mut grid = Grid::new(size, size);
+                for (i, cell) in cells.into_iter().enumerate() {
+                    grid.set(i / size, i % size, cell);
+                }
+                Ok(grid)
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use iced::mouse;
+use iced::widget::canvas::{self, event, Event, Frame, Geometry, Path, Stroke};
+use iced::widget::image::{FilterMethod, Handle};
+use iced::{color, Color, Point, Rectangle, Renderer, Size, Theme};
+
+use conway::{Grid, Rule};
+
+use crate::Message;
+
+// Dessine la grille sur un canvas : une image d'un pixel par cellule, agrandie
+// sans lissage, pour que les grandes grilles restent affichables.
+// En hexagonal, chaque cellule est un hexagone pointe en haut et les lignes
+// impaires sont décalées d'une demi-cellule vers la droite ; quand les
+// hexagones sont trop petits pour être dessinés un à un, l'image donne deux
+// pixels à chaque cellule pour rendre le décalage.
+pub struct GridView<'a> {
+    pub grid: &'a Grid,
+    pub rule: Rule,
+    pub grid_state: bool,
+    pub hexagonal: bool,
+}
+
+// Distance entre deux lignes d'hexagones et distance du centre d'un hexagone
+// à ses pointes, en largeurs de cellule.
+const HEX_ROW_HEIGHT: f32 = 0.866_025_4;
+const HEX_RADIUS: f32 = 0.577_350_3;
+
+// Couleur de chaque état : blanc pour les cellules mortes, noir pour les
+// vivantes, puis un dégradé de bleus qui pâlit pour les cellules mourantes.
+// WireWorld a ses propres couleurs : têtes bleues, queues rouges et
+// conducteurs orange ; les variantes multicolores sont en rouge, bleu, vert
+// et jaune, et les valeurs d'un automate continu forment un dégradé.
+pub fn palette(rule: &Rule) -> Vec<[u8; 3]> {
+    if rule.is_wireworld() {
+        return vec![[255, 255, 255], [30, 90, 255], [230, 40, 30], [255, 170, 0]];
+    }
+    if rule.is_continuous() {
+        return continuous_palette(rule.states());
+    }
+    if rule.colours() > 0 {
+        let colours = [[220, 40, 40], [40, 80, 220], [30, 160, 60], [240, 190, 0]];
+        return std::iter::once([255, 255, 255])
+            .chain(colours.into_iter().take(rule.colours() as usize))
+            .collect();
+    }
+    let mut colors = vec![[255, 255, 255], [0, 0, 0]];
+    let dying = rule.states().saturating_sub(2) as u32;
+    let steps = dying.saturating_sub(1).max(1);
+    for i in 0..dying {
+        let t = |from: u32, to: u32| (from + (to - from) * i / steps) as u8;
+        colors.push([t(40, 190), t(90, 210), t(200, 240)]);
+    }
+    colors
+}
+
+// Dégradé des valeurs d'un automate continu, du blanc au violet sombre en
+// passant par le jaune et le rouge.
+fn continuous_palette(states: u8) -> Vec<[u8; 3]> {
+    let stops = [[255, 255, 255], [250, 210, 60], [220, 60, 40], [70, 0, 90]];
+    let last = (states - 1) as f32;
+    (0..states)
+        .map(|state| {
+            let t = state as f32 / last * (stops.len() - 1) as f32;
+            let i = (t as usize).min(stops.len() - 2);
+            let f = t - i as f32;
+            let [from, to] = [stops[i], stops[i + 1]];
+            [0, 1, 2].map(|c| (from[c] as f32 + (to[c] as f32 - from[c] as f32) * f) as u8)
+        })
+        .collect()
+}
+
+// Nom de chaque état pour les règles dont on dessine les cellules état par
+// état plutôt qu'en les allumant et en les éteignant.
+pub fn state_names(rule: &Rule) -> Option<&'static [&'static str]> {
+    if rule.is_wireworld() {
+        Some(&["Vide", "Tête d'électron", "Queue d'électron", "Conducteur"])
+    } else if rule.colours() > 0 {
+        let names: &[&str] = &["Morte", "Rouge", "Bleue", "Verte", "Jaune"];
+        Some(&names[..=rule.colours() as usize])
+    } else {
+        None
+    }
+}
+
+impl GridView<'_> {
+    // Largeur et hauteur de la grille, en largeurs de cellule.
+    fn extent(&self) -> (f32, f32) {
+        let (width, height) = (self.grid.width() as f32, self.grid.height() as f32);
+        if self.hexagonal {
+            (
+                width + 0.5,
+                (height - 1.0) * HEX_ROW_HEIGHT + 2.0 * HEX_RADIUS,
+            )
+        } else {
+            (width, height)
+        }
+    }
+
+    // Taille d'une cellule à l'écran, les cellules gardant leurs proportions.
+    fn cell_size(&self, bounds: Size) -> f32 {
+        let (width, height) = self.extent();
+        (bounds.width / width).min(bounds.height / height)
+    }
+
+    fn hex_centre(x: usize, y: usize) -> Point {
+        Point::new(
+            x as f32 + 0.5 + 0.5 * (y % 2) as f32,
+            HEX_RADIUS + y as f32 * HEX_ROW_HEIGHT,
+        )
+    }
+
+    // Cellule sous un point exprimé en largeurs de cellule. En hexagonal,
+    // c'est celle dont le centre est le plus proche, parmi la ligne la plus
+    // proche et ses deux voisines.
+    fn cell_at(&self, position: Point) -> Option<(usize, usize)> {
+        let (width, height) = (self.grid.width() as isize, self.grid.height() as isize);
+        if !self.hexagonal {
+            let (x, y) = (position.x as isize, position.y as isize);
+            return ((0..width).contains(&x) && (0..height).contains(&y))
+                .then_some((x as usize, y as usize));
+        }
+        let distance = |&(x, y): &(usize, usize)| position.distance(Self::hex_centre(x, y));
+        let row = ((position.y - HEX_RADIUS) / HEX_ROW_HEIGHT).round() as isize;
+        (row - 1..=row + 1)
+            .filter(|y| (0..height).contains(y))
+            .filter_map(|y| {
+                let x = (position.x - 0.5 - 0.5 * (y % 2) as f32).round() as isize;
+                (0..width).contains(&x).then_some((x as usize, y as usize))
+            })
+            .filter(|cell| distance(cell) <= HEX_RADIUS)
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+    }
+
+    fn image(&self) -> Handle {
+        let colors = palette(&self.rule);
+        let color =
+            |x: usize, y: usize| colors[(self.grid.get(x, y).state as usize).min(colors.len() - 1)];
+        let repeat = if self.hexagonal { 2 } else { 1 };
+        let image_width = repeat * self.grid.width() + repeat - 1;
+        let mut pixels = Vec::with_capacity(image_width * self.grid.height() * 4);
+        for y in 0..self.grid.height() {
+            let mut row: Vec<[u8; 3]> = (0..self.grid.width())
+                .flat_map(|x| std::iter::repeat_n(color(x, y), repeat))
+                .collect();
+            if self.hexagonal {
+                row.insert(if y % 2 == 1 { 0 } else { row.len() }, colors[0]);
+            }
+            for [r, g, b] in row {
+                pixels.extend_from_slice(&[r, g, b, 255]);
+            }
+        }
+        Handle::from_rgba(image_width as u32, self.grid.height() as u32, pixels)
+    }
+
+    fn hexagon(path: &mut canvas::path::Builder, x: usize, y: usize, cell_size: f32) {
+        let centre = Self::hex_centre(x, y);
+        let (cx, cy) = (centre.x * cell_size, centre.y * cell_size);
+        let (half_width, radius) = (0.5 * cell_size, HEX_RADIUS * cell_size);
+        path.move_to(Point::new(cx, cy - radius));
+        path.line_to(Point::new(cx + half_width, cy - radius / 2.0));
+        path.line_to(Point::new(cx + half_width, cy + radius / 2.0));
+        path.line_to(Point::new(cx, cy + radius));
+        path.line_to(Point::new(cx - half_width, cy + radius / 2.0));
+        path.line_to(Point::new(cx - half_width, cy - radius / 2.0));
+        path.close();
+    }
+
+    // Un chemin par état, fait de tous les hexagones des cellules dans cet
+    // état, sur un fond de cellules mortes.
+    fn draw_hexagons(&self, frame: &mut Frame, cell_size: f32, size: Size) {
+        let colors = palette(&self.rule);
+        let [r, g, b] = colors[0];
+        frame.fill_rectangle(Point::ORIGIN, size, Color::from_rgb8(r, g, b));
+        let last = colors.len() - 1;
+        for (state, &[r, g, b]) in colors.iter().enumerate().skip(1) {
+            let cells = Path::new(|path| {
+                for y in 0..self.grid.height() {
+                    for x in 0..self.grid.width() {
+                        if (self.grid.get(x, y).state as usize).min(last) == state {
+                            Self::hexagon(path, x, y, cell_size);
+                        }
+                    }
+                }
+            });
+            frame.fill(&cells, Color::from_rgb8(r, g, b));
+        }
+    }
+}
+
+impl canvas::Program<Message> for GridView<'_> {
+    type State = ();
+
+    fn update(
+        &self,
+        _state: &mut (),
+        event: Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) = event else {
+            return (event::Status::Ignored, None);
+        };
+        let Some(position) = cursor.position_in(bounds) else {
+            return (event::Status::Ignored, None);
+        };
+        let cell_size = self.cell_size(bounds.size());
+        let position = Point::new(position.x / cell_size, position.y / cell_size);
+        if let Some((x, y)) = self.cell_at(position) {
+            (
+                event::Status::Captured,
+                Some(Message::ActiverDésactiver(x, y)),
+            )
+        } else {
+            (event::Status::Ignored, None)
+        }
+    }
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let cell_size = self.cell_size(bounds.size());
+        let (width, height) = self.extent();
+        let size = Size::new(cell_size * width, cell_size * height);
+
+        // En dessous de quelques pixels par cellule, les hexagones et la
+        // grille cacheraient tout.
+        if self.hexagonal && cell_size >= 4.0 {
+            self.draw_hexagons(&mut frame, cell_size, size);
+        } else {
+            frame.draw_image(
+                Rectangle::new(Point::ORIGIN, size),
+                canvas::Image::new(self.image()).filter_method(FilterMethod::Nearest),
+            );
+        }
+
+        if self.grid_state && cell_size >= 4.0 {
+            let lines = Path::new(|path| {
+                if self.hexagonal {
+                    for y in 0..self.grid.height() {
+                        for x in 0..self.grid.width() {
+                            Self::hexagon(path, x, y, cell_size);
+                        }
+                    }
+                    return;
+                }
+                for x in 0..=self.grid.width() {
+                    let x = x as f32 * cell_size;
+                    path.move_to(Point::new(x, 0.0));
+                    path.line_to(Point::new(x, size.height));
+                }
+                for y in 0..=self.grid.height() {
+                    let y = y as f32 * cell_size;
+                    path.move_to(Point::new(0.0, y));
+                    path.line_to(Point::new(size.width, y));
+                }
+            });
+            frame.stroke(
+                &lines,
+                Stroke::default()
+                    .with_color(color!(0xBFBFBF))
+                    .with_width(1.0),
+            );
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::grid::Cell;
+use crate::rule::{Rule, MOORE_NEIGHBOURS};
+use crate::sparse::SparseGrid;
+
+type NodeId = u32;
+
+// Noeud de l'arbre quaternaire : un carré de 2^level cellules de côté, formé
+// de ses quatre quarts [nord-ouest, nord-est, sud-ouest, sud-est]. Les noeuds
+// sont partagés : deux carrés identiques ont le même identifiant.
+#[derive(Clone, Copy)]
+struct Node {
+    children: [NodeId; 4],
+    level: u8,
+    population: u64,
+}
+
+// Moteur HashLife : avance un motif du plan infini de 2^k générations d'un
+// coup en mémorisant le devenir de chaque carré déjà rencontré. Les tables
+// sont conservées d'un saut à l'autre tant que la règle ne change pas.
+#[derive(Clone)]
+pub struct HashLife {
+    rule: Rule,
+    nodes: Vec<Node>,
+    table: HashMap<[NodeId; 4], NodeId>,
+    results: HashMap<(NodeId, u8), NodeId>,
+    empty: Vec<NodeId>,
+}
+
+impl HashLife {
+    const DEAD: NodeId = 0;
+    const ALIVE: NodeId = 1;
+    // Au-delà, les tables sont vidées avant le saut suivant.
+    const MAX_NODES: usize = 1 << 23;
+    pub const MAX_JUMP: u8 = 48;
+
+    pub fn new(rule: Rule) -> Self {
+        let leaf = |population| Node {
+            children: [Self::DEAD; 4],
+            level: 0,
+            population,
+        };
+        HashLife {
+            rule,
+            nodes: vec![leaf(0), leaf(1)],
+            table: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![Self::DEAD],
+        }
+    }
+
+    // HashLife suppose deux états, des voisins à distance 1 et qu'une zone
+    // vide le reste.
+    pub fn supports(rule: &Rule) -> bool {
+        rule.states() == 2
+            && !rule.is_larger_than_life()
+            && !rule.is_elementary()
+            && rule.next_state_for(0, 0) == 0
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    // Avance les cellules vivantes de `sparse` de 2^k générations.
+    pub fn jump(&mut self, sparse: &SparseGrid, k: u8) -> SparseGrid {
+        assert!(
+            k <= Self::MAX_JUMP,
+            "saut de 2^{} générations trop grand",
+            k
+        );
+        if self.nodes.len() > Self::MAX_NODES {
+            *self = HashLife::new(self.rule);
+        }
+
+        let cells: Vec<(i64, i64)> = sparse
+            .cells()
+            .filter(|(_, cell)| cell.is_alive())
+            .map(|(position, _)| position)
+            .collect();
+        let Some((x_min, y_min, x_max, y_max)) = sparse.bounding_box() else {
+            return SparseGrid::new();
+        };
+
+        let size = (x_max - x_min).max(y_max - y_min) as u64 + 1;
+        let mut level = (size.next_power_of_two().trailing_zeros() as u8).max(1);
+        let mut origin = (x_min, y_min);
+        let mut root = self.build(&cells, origin, level);
+
+        // Le motif doit tenir dans le carré central, avec une marge suffisante
+        // pour qu'il ne puisse pas en sortir pendant le saut.
+        while level < k + 2 || !self.is_centred(root) {
+            (root, origin, level) = self.expand(root, origin, level);
+        }
+        (root, origin, level) = self.expand(root, origin, level);
+
+        let result = self.result(root, k);
+        let offset = 1i64 << (level - 2);
+        let mut next = SparseGrid::new();
+        self.collect(result, (origin.0 + offset, origin.1 + offset), &mut next);
+        next
+    }
+
+    fn population(&self, id: NodeId) -> u64 {
+        self.nodes[id as usize].population
+    }
+
+    fn children(&self, id: NodeId) -> [NodeId; 4] {
+        self.nodes[id as usize].children
+    }
+
+    fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(&id) = self.table.get(&children) {
+            return id;
+        }
+        let node = Node {
+            children,
+            level: self.nodes[children[0] as usize].level + 1,
+            population: children.iter().map(|&child| self.population(child)).sum(),
+        };
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(node);
+        self.table.insert(children, id);
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let smaller = *self.empty.last().unwrap();
+            let node = self.join([smaller; 4]);
+            self.empty.push(node);
+        }
+        self.empty[level as usize]
+    }
+
+    // Carré central, de moitié moins large.
+    fn centre(&mut self, id: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(id);
+        self.join([
+            self.children(nw)[3],
+            self.children(ne)[2],
+            self.children(sw)[1],
+            self.children(se)[0],
+        ])
+    }
+
+    // Vrai si toutes les cellules vivantes sont dans le carré central.
+    fn is_centred(&mut self, id: NodeId) -> bool {
+        let centre = self.centre(id);
+        self.population(centre) == self.population(id)
+    }
+
+    // Entoure le carré de vide : même contenu, deux fois plus large.
+    fn expand(&mut self, id: NodeId, origin: (i64, i64), level: u8) -> (NodeId, (i64, i64), u8) {
+        let e = self.empty(level - 1);
+        let [nw, ne, sw, se] = self.children(id);
+        let root = [
+            self.join([e, e, e, nw]),
+            self.join([e, e, ne, e]),
+            self.join([e, sw, e, e]),
+            self.join([se, e, e, e]),
+        ];
+        let half = 1i64 << (level - 1);
+        (
+            self.join(root),
+            (origin.0 - half, origin.1 - half),
+            level + 1,
+        )
+    }
+
+    fn build(&mut self, cells: &[(i64, i64)], origin: (i64, i64), level: u8) -> NodeId {
+        if cells.is_empty() {
+            return self.empty(level);
+        }
+        if level == 0 {
+            return Self::ALIVE;
+        }
+        let half = 1i64 << (level - 1);
+        let mut quarters: [Vec<(i64, i64)>; 4] = Default::default();
+        for &(x, y) in cells {
+            let east = (x >= origin.0 + half) as usize;
+            let south = (y >= origin.1 + half) as usize;
+            quarters[2 * south + east].push((x, y));
+        }
+        let mut children = [Self::DEAD; 4];
+        for (i, quarter) in quarters.iter().enumerate() {
+            let corner = (
+                origin.0 + half * (i % 2) as i64,
+                origin.1 + half * (i / 2) as i64,
+            );
+            children[i] = self.build(quarter, corner, level - 1);
+        }
+        self.join(children)
+    }
+
+    fn collect(&self, id: NodeId, origin: (i64, i64), sparse: &mut SparseGrid) {
+        let node = self.nodes[id as usize];
+        if node.population == 0 {
+            return;
+        }
+        if node.level == 0 {
+            sparse.set(origin.0, origin.1, Cell::ALIVE);
+            return;
+        }
+        let half = 1i64 << (node.level - 1);
+        for (i, &child) in node.children.iter().enumerate() {
+            let corner = (
+                origin.0 + half * (i % 2) as i64,
+                origin.1 + half * (i / 2) as i64,
+            );
+            self.collect(child, corner, sparse);
+        }
+    }
+
+    // Carré central d'un noeud de niveau >= 2, avancé de 2^step générations,
+    // avec step <= niveau - 2.
+    fn result(&mut self, id: NodeId, step: u8) -> NodeId {
+        let level = self.nodes[id as usize].level;
+        if self.population(id) == 0 {
+            return self.empty(level - 1);
+        }
+        if let Some(&result) = self.results.get(&(id, step)) {
+            return result;
+        }
+
+        let result = if level == 2 {
+            self.step_4x4(id)
+        } else {
+            let [nw, ne, sw, se] = self.children(id);
+            let [_, nw_ne, nw_sw, nw_se] = self.children(nw);
+            let [ne_nw, _, ne_sw, ne_se] = self.children(ne);
+            let [sw_nw, sw_ne, _, sw_se] = self.children(sw);
+            let [se_nw, se_ne, se_sw, _] = self.children(se);
+
+            // Les neuf carrés de niveau - 1 qui se chevauchent.
+            let n01 = self.join([nw_ne, ne_nw, nw_se, ne_sw]);
+            let n10 = self.join([nw_sw, nw_se, sw_nw, sw_ne]);
+            let n11 = self.join([nw_se, ne_sw, sw_ne, se_nw]);
+            let n12 = self.join([ne_sw, ne_se, se_nw, se_ne]);
+            let n21 = self.join([sw_ne, se_nw, sw_se, se_sw]);
+            let squares = [nw, n01, ne, n10, n11, n12, sw, n21, se];
+
+            // A pleine vitesse, deux demi-sauts de 2^(level - 3) ; sinon un
+            // seul saut puis on recentre.
+            let full_speed = step == level - 2;
+            let first_step = if full_speed { level - 3 } else { step };
+            let mut r = [Self::DEAD; 9];
+            for (i, &square) in squares.iter().enumerate() {
+                r[i] = self.result(square, first_step);
+            }
+
+            let mut quarters = [Self::DEAD; 4];
+            for (i, quarter) in quarters.iter_mut().enumerate() {
+                let (row, column) = (i / 2, i % 2);
+                let top = 3 * row + column;
+                let joined = self.join([r[top], r[top + 1], r[top + 3], r[top + 4]]);
+                *quarter = if full_speed {
+                    self.result(joined, level - 3)
+                } else {
+                    self.centre(joined)
+                };
+            }
+            self.join(quarters)
+        };
+
+        self.results.insert((id, step), result);
+        result
+    }
+
+    // Cas de base : le carré central 2x2 d'un carré 4x4 après une génération.
+    fn step_4x4(&mut self, id: NodeId) -> NodeId {
+        let mut cells = [[false; 4]; 4];
+        for (i, &quarter) in self.children(id).iter().enumerate() {
+            for (j, &leaf) in self.children(quarter).iter().enumerate() {
+                let x = 2 * (i % 2) + j % 2;
+                let y = 2 * (i / 2) + j / 2;
+                cells[y][x] = leaf == Self::ALIVE;
+            }
+        }
+
+        let mut next = [Self::DEAD; 4];
+        for (i, leaf) in next.iter_mut().enumerate() {
+            let (x, y) = (1 + i % 2, 1 + i / 2);
+            let neighbours = MOORE_NEIGHBOURS
+                .iter()
+                .enumerate()
+                .filter(|(_, (dx, dy))| {
+                    cells[(y as isize + dy) as usize][(x as isize + dx) as usize]
+                })
+                .fold(0, |configuration, (i, _)| configuration | 1 << i);
+            let state = self.rule.next_state_for(cells[y][x] as u8, neighbours);
+            if state == 1 {
+                *leaf = Self::ALIVE;
+            }
+        }
+        self.join(next)
+    }
+}
+
+impl fmt::Debug for HashLife {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HashLife")
+            .field("rule", &self.rule)
+            .field("nodes", &self.nodes.len())
+            .field("results", &self.results.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::grid::Grid;
+    use crate::neighbourhood::Neighbourhood;
+
+    #[test]
+    fn jumps_match_steps() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for rule in ["B3/S23", "B36/S23", "B2n3/S23-q"] {
+            let rule: Rule = rule.parse().unwrap();
+            let mut hashlife = HashLife::new(rule);
+            let soup = Grid::random_with_density(24, 20, 0.4, &mut rng);
+            let mut expected = SparseGrid::from_grid(&soup, -12, -7);
+            let mut jumped = expected.clone();
+            // Les tables gardées d'un saut à l'autre servent aux suivants.
+            for k in [0, 1, 2, 3, 5, 0, 4, 6] {
+                for _ in 0..1 << k {
+                    expected = expected.step(&rule, Neighbourhood::Moore);
+                }
+                jumped = hashlife.jump(&jumped, k);
+                assert_eq!(jumped, expected, "{}, saut de 2^{}", rule, k);
+            }
+        }
+    }
+}
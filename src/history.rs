@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+
+use crate::grid::Cell;
+
+// Ce qu'une action a changé : le nombre de générations écoulées, l'état
+// avant et après de chaque cellule modifiée, repérée sur le plan infini ou
+// dans la grille, de chaque mot de 64 cellules d'une ligne modifié par la
+// grille de bits, repéré par sa première cellule, et pour un automate
+// continu, chaque valeur modifiée. Les naissances et les morts d'une
+// génération sont gardées pour être rendues quand elle est rétablie.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Change {
+    pub(crate) generations: u64,
+    pub(crate) births_and_deaths: Option<(u32, u32)>,
+    pub(crate) cells: Vec<((i64, i64), Cell, Cell)>,
+    pub(crate) words: Vec<((usize, usize), u64, u64)>,
+    pub(crate) values: Vec<(usize, f32, f32)>,
+}
+
+impl Change {
+    fn size(&self) -> usize {
+        self.cells.len() + self.words.len() + self.values.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.generations == 0 && self.size() == 0
+    }
+}
+
+// Dernières actions, à annuler une à une, et actions annulées, à rétablir.
+// Au-delà de `capacity` actions ou de MAX_STORED cellules, mots et valeurs
+// gardés (quelques dizaines de Mo), les plus anciennes sont oubliées.
+#[derive(Debug, Clone)]
+pub(crate) struct History {
+    capacity: usize,
+    done: VecDeque<Change>,
+    undone: Vec<Change>,
+    stored: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new(History::DEFAULT_CAPACITY)
+    }
+}
+
+impl History {
+    pub(crate) const DEFAULT_CAPACITY: usize = 1000;
+    const MAX_STORED: usize = 1 << 21;
+
+    pub(crate) fn new(capacity: usize) -> History {
+        History {
+            capacity,
+            done: VecDeque::new(),
+            undone: Vec::new(),
+            stored: 0,
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub(crate) fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub(crate) fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    // Une nouvelle action rend impossible de rétablir celles annulées.
+    pub(crate) fn record(&mut self, change: Change) {
+        if !self.is_enabled() || change.is_empty() {
+            return;
+        }
+        self.undone.clear();
+        self.push_done(change);
+    }
+
+    fn push_done(&mut self, change: Change) {
+        self.stored += change.size();
+        self.done.push_back(change);
+        while self.done.len() > self.capacity
+            || (self.stored > Self::MAX_STORED && self.done.len() > 1)
+        {
+            if let Some(oldest) = self.done.pop_front() {
+                self.stored -= oldest.size();
+            }
+        }
+    }
+
+    // Dernière action, à défaire puis à rendre par `undone`.
+    pub(crate) fn take_last(&mut self) -> Option<Change> {
+        let change = self.done.pop_back()?;
+        self.stored -= change.size();
+        Some(change)
+    }
+
+    pub(crate) fn undone(&mut self, change: Change) {
+        self.undone.push(change);
+    }
+
+    // Dernière action annulée, à refaire puis à rendre par `redone`.
+    pub(crate) fn take_next(&mut self) -> Option<Change> {
+        self.undone.pop()
+    }
+
+    pub(crate) fn redone(&mut self, change: Change) {
+        self.push_done(change);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.done.clear();
+        self.undone.clear();
+        self.stored = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(size: usize) -> Change {
+        Change {
+            generations: 1,
+            words: vec![((0, 0), 0, 0); size],
+            ..Change::default()
+        }
+    }
+
+    #[test]
+    fn oldest_changes_are_forgotten() {
+        let mut history = History::new(3);
+        for _ in 0..5 {
+            history.record(change(1));
+        }
+        assert_eq!(history.done.len(), 3);
+        assert_eq!(history.stored, 3);
+
+        // Au-delà de MAX_STORED, seule la dernière action reste, même plus
+        // grosse à elle seule.
+        let half = History::MAX_STORED / 2 + 1;
+        history.record(change(half));
+        history.record(change(half));
+        assert_eq!(history.done.len(), 1);
+        assert_eq!(history.stored, half);
+        history.record(change(History::MAX_STORED + 1));
+        assert_eq!(history.done.len(), 1);
+        assert_eq!(history.stored, History::MAX_STORED + 1);
+
+        let last = history.take_last().unwrap();
+        assert_eq!(history.stored, 0);
+        history.undone(last);
+        let next = history.take_next().unwrap();
+        history.redone(next);
+        assert_eq!(history.stored, History::MAX_STORED + 1);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn a_new_change_forgets_the_undone_ones() {
+        let mut history = History::default();
+        history.record(change(1));
+        history.record(change(2));
+        let last = history.take_last().unwrap();
+        history.undone(last);
+        assert!(history.can_redo());
+        history.record(Change::default());
+        assert!(history.can_redo());
+        history.record(change(3));
+        assert!(!history.can_redo());
+        assert_eq!(history.stored, 4);
+    }
+}
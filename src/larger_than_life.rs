@@ -0,0 +1,91 @@
+use crate::bands::map_bands;
+use crate::grid::Grid;
+use crate::rule::Rule;
+use crate::topology::Topology;
+
+// Table des sommes cumulées de la grille bordée de `margin` cellules de
+// chaque côté, lues à travers la topologie : `sums` donne pour chaque (x, y)
+// le nombre de cellules vivantes dans le rectangle [0, x) x [0, y) de la
+// grille bordée. Le nombre de cellules vivantes d'un rectangle se lit alors
+// en quatre accès, quelle que soit sa taille.
+struct SummedArea {
+    width: usize,
+    sums: Vec<u32>,
+}
+
+impl SummedArea {
+    fn new(grid: &Grid, topology: Topology, margin: usize) -> SummedArea {
+        let width = grid.width() + 2 * margin + 1;
+        let height = grid.height() + 2 * margin + 1;
+        let mut sums = vec![0; width * height];
+        for y in 1..height {
+            let mut row_sum = 0;
+            for x in 1..width {
+                let cell = topology.resolve(
+                    x as isize - 1 - margin as isize,
+                    y as isize - 1 - margin as isize,
+                    grid.width(),
+                    grid.height(),
+                );
+                if cell.is_some_and(|(x, y)| grid.is_alive(x, y)) {
+                    row_sum += 1;
+                }
+                sums[y * width + x] = sums[(y - 1) * width + x] + row_sum;
+            }
+        }
+        SummedArea { width, sums }
+    }
+
+    // Cellules vivantes du rectangle [x0, x1) x [y0, y1) de la grille bordée.
+    fn sum(&self, x0: usize, y0: usize, x1: usize, y1: usize) -> u32 {
+        let at = |x: usize, y: usize| self.sums[y * self.width + x];
+        at(x1, y1) + at(x0, y0) - at(x0, y1) - at(x1, y0)
+    }
+}
+
+// Génération suivante d'une règle Larger than Life ; renvoie la grille et
+// son nombre de cellules vivantes. Un voisinage carré se lit d'un coup dans
+// la table des sommes cumulées, un losange ligne par ligne.
+pub(crate) fn step(grid: &Grid, rule: &Rule, topology: Topology, threads: usize) -> (Grid, u32) {
+    let range = rule
+        .larger_than_life()
+        .expect("règle Larger than Life attendue");
+    let radius = range.radius as usize;
+    let table = SummedArea::new(grid, topology, radius);
+    let width = grid.width();
+
+    let mut next = grid.clone();
+    let populations = map_bands(next.cells_mut(), width, threads, |first_row, band| {
+        let mut population = 0;
+        for (dy, row) in band.chunks_mut(width).enumerate() {
+            let y = first_row + dy;
+            for (x, cell) in row.iter_mut().enumerate() {
+                // Dans la grille bordée, la cellule est en (x + radius, y + radius).
+                let mut count = if range.von_neumann {
+                    (0..=2 * radius)
+                        .map(|row| {
+                            let span = radius - row.abs_diff(radius);
+                            table.sum(
+                                x + radius - span,
+                                y + row,
+                                x + radius + span + 1,
+                                y + row + 1,
+                            )
+                        })
+                        .sum()
+                } else {
+                    table.sum(x, y, x + 2 * radius + 1, y + 2 * radius + 1)
+                };
+                if !range.middle && cell.is_alive() {
+                    count -= 1;
+                }
+                cell.state = rule.next_state(cell.state, count as usize);
+                if cell.is_alive() {
+                    population += 1;
+                }
+            }
+        }
+        population
+    });
+    (next, populations.into_iter().sum())
+}
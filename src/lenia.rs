@@ -0,0 +1,181 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bands::map_bands;
+use crate::grid::{Cell, Grid};
+use crate::topology::Topology;
+
+// Nombre de niveaux non nuls auxquels sont arrondies les valeurs pour
+// l'affichage : l'état d'une cellule de valeur v est v * LEVELS arrondi.
+pub(crate) const LEVELS: u8 = 254;
+
+// Paramètres d'un automate continu de type Lenia. Chaque cellule porte une
+// valeur entre 0 et 1 ; à chaque génération, la moyenne u des valeurs
+// autour d'elle, pondérée par un anneau de rayon `radius`, la fait croître
+// de dt * g(u), où g vaut 1 en u = mu et décroît en cloche de largeur
+// `sigma` jusqu'à -1.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Lenia {
+    pub radius: u8,
+    pub mu: f32,
+    pub sigma: f32,
+    pub dt: f32,
+}
+
+// Les paramètres de l'Orbium, qui se déplace en gardant sa forme.
+impl Default for Lenia {
+    fn default() -> Self {
+        Lenia {
+            radius: 13,
+            mu: 0.15,
+            sigma: 0.015,
+            dt: 0.1,
+        }
+    }
+}
+
+impl Lenia {
+    pub const MAX_RADIUS: u8 = 30;
+
+    pub fn is_valid(&self) -> bool {
+        (1..=Self::MAX_RADIUS).contains(&self.radius)
+            && (0.0..=1.0).contains(&self.mu)
+            && self.sigma > 0.0
+            && self.dt > 0.0
+            && self.dt <= 1.0
+    }
+
+    // Poids de chaque décalage (dx, dy) : une cloche nulle au centre et au
+    // bord du disque de rayon `radius`, maximale à mi-rayon. La somme des
+    // poids vaut 1.
+    fn kernel(&self) -> Vec<(isize, isize, f32)> {
+        let radius = self.radius as isize;
+        let mut kernel = Vec::new();
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let r = ((dx * dx + dy * dy) as f32).sqrt() / radius as f32;
+                if r > 0.0 && r < 1.0 {
+                    kernel.push((dx, dy, (4.0 - 1.0 / (r * (1.0 - r))).exp()));
+                }
+            }
+        }
+        let total: f32 = kernel.iter().map(|&(_, _, weight)| weight).sum();
+        for (_, _, weight) in &mut kernel {
+            *weight /= total;
+        }
+        kernel
+    }
+
+    fn growth(&self, u: f32) -> f32 {
+        let d = (u - self.mu) / self.sigma;
+        2.0 * (-d * d / 2.0).exp() - 1.0
+    }
+
+    // Génération suivante des valeurs de `field`.
+    pub(crate) fn step(&self, field: &Field, topology: Topology, threads: usize) -> Field {
+        let kernel = self.kernel();
+        let (width, height) = (field.width, field.height);
+        let radius = self.radius as usize;
+        // Loin des bords, les voisins se lisent par un décalage fixe dans
+        // le tableau, sans passer par la topologie.
+        let shifts: Vec<(isize, f32)> = kernel
+            .iter()
+            .map(|&(dx, dy, weight)| (dy * width as isize + dx, weight))
+            .collect();
+
+        let mut next = field.clone();
+        map_bands(&mut next.values, width, threads, |first_row, band| {
+            for (dy, row) in band.chunks_mut(width).enumerate() {
+                let y = first_row + dy;
+                for (x, value) in row.iter_mut().enumerate() {
+                    let inside =
+                        x >= radius && y >= radius && x + radius < width && y + radius < height;
+                    let u: f32 = if inside {
+                        let i = (y * width + x) as isize;
+                        shifts
+                            .iter()
+                            .map(|&(shift, weight)| weight * field.values[(i + shift) as usize])
+                            .sum()
+                    } else {
+                        kernel
+                            .iter()
+                            .filter_map(|&(dx, dy, weight)| {
+                                let neighbour = topology.resolve(
+                                    x as isize + dx,
+                                    y as isize + dy,
+                                    width,
+                                    height,
+                                );
+                                neighbour.map(|(nx, ny)| weight * field.get(nx, ny))
+                            })
+                            .sum()
+                    };
+                    *value = (*value + self.dt * self.growth(u)).clamp(0.0, 1.0);
+                }
+            }
+        });
+        next
+    }
+}
+
+impl fmt::Display for Lenia {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "R{}, μ {}, σ {}, dt {}",
+            self.radius, self.mu, self.sigma, self.dt
+        )
+    }
+}
+
+// Valeurs des cellules d'un automate continu, rangées ligne par ligne.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Field {
+    width: usize,
+    height: usize,
+    values: Vec<f32>,
+}
+
+impl Field {
+    pub(crate) fn from_grid(grid: &Grid) -> Field {
+        let mut values = Vec::with_capacity(grid.width() * grid.height());
+        for y in 0..grid.height() {
+            values.extend(
+                grid.row(y)
+                    .iter()
+                    .map(|cell| cell.state.min(LEVELS) as f32 / LEVELS as f32),
+            );
+        }
+        Field {
+            width: grid.width(),
+            height: grid.height(),
+            values,
+        }
+    }
+
+    pub(crate) fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    pub(crate) fn values_mut(&mut self) -> &mut [f32] {
+        &mut self.values
+    }
+
+    fn get(&self, x: usize, y: usize) -> f32 {
+        self.values[y * self.width + x]
+    }
+
+    pub(crate) fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        self.values[y * self.width + x] = cell.state.min(LEVELS) as f32 / LEVELS as f32;
+    }
+
+    // Les valeurs arrondies à l'un des LEVELS niveaux, pour l'affichage.
+    pub(crate) fn to_grid(&self) -> Grid {
+        let mut grid = Grid::new(self.width, self.height);
+        for (cell, value) in grid.cells_mut().iter_mut().zip(&self.values) {
+            cell.state = (value * LEVELS as f32).round() as u8;
+        }
+        grid
+    }
+}
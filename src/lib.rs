@@ -0,0 +1,39 @@
+// Moteur du jeu de la vie, sans dépendance à l'interface graphique.
+
+mod bands;
+mod bitgrid;
+mod census;
+mod cycles;
+mod elementary;
+mod grid;
+mod hashlife;
+mod history;
+mod larger_than_life;
+mod lenia;
+mod multicolour;
+mod neighbourhood;
+mod periodic_search;
+mod predecessor;
+mod rule;
+mod simulation;
+mod soup;
+mod sparse;
+mod stochastic;
+mod tiles;
+mod topology;
+
+pub use bitgrid::BitGrid;
+pub use census::{Census, CensusEntry, ObjectKind};
+pub use cycles::Cycle;
+pub use grid::{Cell, Grid};
+pub use hashlife::HashLife;
+pub use lenia::Lenia;
+pub use neighbourhood::{Mask, Neighbourhood};
+pub use periodic_search::{Found, PeriodicSearch, SearchOutcome};
+pub use predecessor::{ParentSearch, Predecessor};
+pub use rule::Rule;
+pub use simulation::Simulation;
+pub use soup::{Soup, SoupSearch};
+pub use sparse::SparseGrid;
+pub use stochastic::Stochastic;
+pub use topology::Topology;
@@ -1,50 +1,209 @@
-use std::fmt;
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::thread;
 
+use iced::futures::channel::oneshot;
 use iced::widget::Image;
 use iced::{
-    color, time,
-    widget::{button, column, container, row, text, text_input, Button, Column, Row, Svg},
-    Border, Color, Element, Length, Subscription, Theme,
+    time,
+    widget::{
+        button, canvas, column, container, row, scrollable, text, text_input, Button, Column, Row,
+        Svg,
+    },
+    Border, Color, Element, Length, Subscription, Task,
 };
 
 use image::{Rgb, RgbImage};
-use rand::Rng;
-use serde::de::{self, SeqAccess, Visitor};
-use serde::Deserializer;
-use serde::{ser::SerializeSeq, Deserialize, Serialize, Serializer};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use conway::{
+    Cell, Census, Grid, Lenia, Mask, Neighbourhood, ParentSearch, PeriodicSearch, Predecessor,
+    Rule, SearchOutcome, Simulation, SoupSearch, Stochastic, Topology,
+};
+
+mod chart;
+mod grid_view;
+mod search;
+
+use chart::{Chart, Series};
+use grid_view::GridView;
+
 fn main() {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("Usage : Conway [--width <largeur>] [--height <hauteur>] [--threads <fils>]");
+            eprintln!(
+                "        Conway --search <soupes> [--density <pourcentage>] [--seed <graine>] [--rule <règle>] [--width <largeur>] [--height <hauteur>] [--threads <fils>]"
+            );
+            std::process::exit(1);
+        }
+    };
+    let Options {
+        width,
+        height,
+        threads,
+        ..
+    } = options;
+    if options.soups > 0 {
+        let search = SoupSearch {
+            width,
+            height,
+            density: options.density as f64 / 100.0,
+            rule: options.rule,
+            max_generations: SOUP_GENERATIONS,
+        };
+        let seed = options.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        if let Err(e) = search::run(search, options.soups, seed, threads) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
     let _ = iced::application(Conway::title, Conway::update, Conway::view)
         .subscription(Conway::subscription)
-        .run();
+        .run_with(move || (Conway::new(width, height, threads), Task::none()));
+}
+
+// Au-delà, une soupe de la recherche est recensée sans s'être stabilisée.
+const SOUP_GENERATIONS: u64 = 100_000;
+
+// Sans --search, l'interface s'ouvre ; la densité, la graine et la règle ne
+// servent qu'à la recherche de soupes.
+struct Options {
+    width: usize,
+    height: usize,
+    threads: usize,
+    soups: usize,
+    density: usize,
+    seed: Option<u64>,
+    rule: Rule,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        width: Grid::DEFAULT_SIZE,
+        height: Grid::DEFAULT_SIZE,
+        threads: Simulation::default_threads(),
+        soups: 0,
+        density: 25,
+        seed: None,
+        rule: Rule::default(),
+    };
+    while let Some(arg) = args.next() {
+        let (target, range) = match arg.as_str() {
+            "--width" => (&mut options.width, Grid::MIN_SIZE..=Grid::MAX_SIZE),
+            "--height" => (&mut options.height, Grid::MIN_SIZE..=Grid::MAX_SIZE),
+            "--threads" => (&mut options.threads, 1..=256),
+            "--search" => (&mut options.soups, 1..=usize::MAX),
+            "--density" => (&mut options.density, 0..=100),
+            "--seed" | "--rule" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("Valeur manquante après {}", arg))?;
+                if arg == "--seed" {
+                    let seed = value
+                        .parse()
+                        .map_err(|_| format!("{} doit être un nombre entier positif", arg))?;
+                    options.seed = Some(seed);
+                } else {
+                    options.rule = value.parse()?;
+                }
+                continue;
+            }
+            _ => return Err(format!("Argument inconnu : {}", arg)),
+        };
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Valeur manquante après {}", arg))?;
+        *target = match value.parse() {
+            Ok(n) if range.contains(&n) => n,
+            _ => {
+                return Err(format!(
+                    "{} doit être un nombre entre {} et {}",
+                    arg,
+                    range.start(),
+                    range.end()
+                ))
+            }
+        };
+    }
+    Ok(options)
+}
+
+// Fait `work` sur un fil à part, pour ne pas figer la fenêtre, et rend son
+// résultat par le message `done`.
+fn in_background<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, String> + Send + 'static,
+    done: fn(Result<T, String>) -> Message,
+) -> Task<Message> {
+    let (sender, receiver) = oneshot::channel();
+    thread::spawn(move || sender.send(work()));
+    Task::perform(receiver, move |result| {
+        done(result.unwrap_or_else(|_| Err("Le calcul s'est arrêté sur une erreur".to_string())))
+    })
 }
 
 #[derive(Clone, Debug)]
 enum Message {
     Update,
+    StepBack,
+    Annuler,
+    Rétablir,
     PlayPause,
     Simulation,
     Settings,
     Examples,
     Réinitialiser,
     Grid,
+    AutoPause,
+    ToggleChart,
+    ChooseChartWindow(Option<u64>),
+    Follow,
+    Jump,
+    IncreaseJump,
+    DecreaseJump,
     IncreaseVitesse,
     DecreaseVitesse,
     IncreaseQuickVitesse,
     DecreaseQuickVitesse,
     ActiverDésactiver(usize, usize),
+    ChooseBrush(u8),
     FillingMethodChanged,
-    InitCellsNumber(u32),
-    InitDensity(u32),
-    ChangeVitesse(u32),
     InputChangeMethod(String),
     InputVitesse(String),
+    InputWidth(String),
+    InputHeight(String),
+    InputRule(String),
     ConvertVitesse,
     ConvertDensity,
     ConvertCells,
+    ConvertDimensions,
+    ConvertRule,
+    ChooseRule(Rule),
+    InputLenia(usize, String),
+    ConvertLenia,
+    InputStochastic(usize, String),
+    ConvertStochastic,
+    ChooseNeighbourhood(Neighbourhood),
+    ToggleMask(isize, isize),
+    ChooseTopology(Topology),
     Sauvegarder,
+    ExporterPng,
+    ExporterCsv,
+    Recenser,
+    ExporterRecensement,
+    SearchScreen,
+    InputSearch(usize, String),
+    Chercher,
+    RechercheFinie(Result<SearchOutcome, String>),
+    ChargerMotif(usize),
+    TrouverParent,
+    ParentTrouve(Result<Predecessor, String>),
     ChargerEScreen,
     ChargerEFinal(usize),
     ChargerSScreen,
@@ -61,100 +220,92 @@ enum Screen {
     Conway,
     ExamplesC,
     SavesC,
+    Census,
+    Search,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-struct Cell {
-    living: bool,
-}
-#[derive(Debug, Clone, Copy)]
-struct Tab([[Cell; Conway::SIZE]; Conway::SIZE]);
-
-impl Serialize for Tab {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut seq = serializer.serialize_seq(Some(Conway::SIZE * Conway::SIZE))?;
-        for row in &self.0 {
-            for cell in row {
-                seq.serialize_element(&cell)?;
-            }
-        }
-
-        seq.end()
-    }
-}
-
-impl<'de> Deserialize<'de> for Tab {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        struct TabVisitor;
-
-        impl<'de> Visitor<'de> for TabVisitor {
-            type Value = Tab;
-
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                write!(
-                    formatter,
-                    "a 2D array of size {}x{}",
-                    Conway::SIZE,
-                    Conway::SIZE
-                )
-            }
-
-            fn visit_seq<A>(self, mut seq: A) -> Result<Tab, A::Error>
-            where
-                A: SeqAccess<'de>,
-            {
-                let mut cells = [[Cell { living: false }; Conway::SIZE]; Conway::SIZE];
-                for i in 0..Conway::SIZE {
-                    for j in 0..Conway::SIZE {
-                        let cell: Cell = seq.next_element()?.ok_or_else(|| {
-                            de::Error::invalid_length(i * Conway::SIZE + j, &self)
-                        })?;
-                        cells[i][j] = cell;
-                    }
-                }
-                Ok(Tab(cells))
-            }
-        }
-
-        deserializer.deserialize_seq(TabVisitor)
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone)]
 struct Conway {
     nb_init_cells: u32,
-    cells_tab: Tab,
+    #[serde(flatten)]
+    simulation: Simulation,
     playing: bool,
-    generation: u64,
     screen: Screen,
     filling_method: bool,
     living_density: u32,
-    number_of_living_cells: u32,
-    initial_tab: Tab,
+    initial_simulation: Simulation,
+    width: usize,
+    height: usize,
+    #[serde(skip)]
+    threads: usize,
+    // Déjà sauvegardés avec la simulation.
+    #[serde(skip)]
+    rule: Rule,
+    #[serde(skip)]
+    neighbourhood: Neighbourhood,
+    #[serde(skip)]
+    topology: Topology,
+    #[serde(skip)]
+    lenia: Lenia,
+    #[serde(skip)]
+    stochastic: Stochastic,
+    // Graine choisie dans les paramètres ; sans elle, chaque simulation en
+    // tire une au hasard.
+    #[serde(skip)]
+    seed: Option<u64>,
     vitesse: u32,
     grid_state: bool,
+    // Met la lecture en pause dès qu'un cycle est reconnu.
+    auto_pause: bool,
+    // Population, naissances et morts de chaque génération calculée, et
+    // nombre de générations montrées par les courbes (toutes si None).
+    #[serde(skip)]
+    series: Series,
+    show_chart: bool,
+    chart_window: Option<u64>,
+    // Dernier recensement des objets de la grille, ou pourquoi il n'a pas
+    // pu être fait.
+    #[serde(skip)]
+    census: Result<Census, String>,
+    // Rectangle, période et déplacement de la recherche d'oscillateurs et de
+    // vaisseaux, et ses résultats une fois lancée.
+    input_search: [String; 5],
+    #[serde(skip)]
+    searching: bool,
+    #[serde(skip)]
+    search: Option<Result<SearchOutcome, String>>,
+    // Recherche d'un parent de la grille en cours, puis son issue, effacée
+    // dès que la grille avance.
+    #[serde(skip)]
+    parent_search: Option<ParentSearch>,
+    #[serde(skip)]
+    parent: Option<Result<Predecessor, String>>,
+    follow: bool,
+    jump_exponent: u8,
+    brush: u8,
     input_v: String,
     input_c: String,
+    input_w: String,
+    input_h: String,
+    input_r: String,
+    input_lenia: [String; 4],
+    input_stochastic: [String; 4],
     erreur_v: bool,
     erreur_c: bool,
+    erreur_d: bool,
+    erreur_r: bool,
+    erreur_l: bool,
+    erreur_s: bool,
     nb_sauvegardes: usize,
     current_frame: usize,
 }
 
 impl Conway {
-    const SIZE: usize = 50;
     fn subscription(&self) -> Subscription<Message> {
         if self.playing {
-            time::every(time::Duration::from_millis(self.vitesse as u64).into())
-                .map(|_| Message::Update)
+            time::every(time::Duration::from_millis(self.vitesse as u64)).map(|_| Message::Update)
         } else {
-            time::every(time::Duration::from_millis(100).into()).map(|_| Message::Tick)
+            time::every(time::Duration::from_millis(100)).map(|_| Message::Tick)
         }
     }
 
@@ -166,75 +317,81 @@ impl Conway {
         paths
     }
 
-    fn check_neighbours(&self, x: usize, y: usize) -> usize {
-        let mut living_neighbours = 0;
-        let _size = Self::SIZE as isize;
-        for dx in -1isize..=1isize {
-            for dy in -1isize..=1isize {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
-
-                let nx = ((x as isize) + dx).rem_euclid(Self::SIZE as isize) as usize;
-                let ny = ((y as isize) + dy).rem_euclid(Self::SIZE as isize) as usize;
+    fn next_seed(&self) -> u64 {
+        self.seed.unwrap_or_else(|| rand::thread_rng().gen())
+    }
 
-                if self.cells_tab.0[nx as usize][ny as usize].living {
-                    living_neighbours += 1;
+    // La grille a été remplie par `rng`, tiré de `seed`.
+    fn new_simulation(&self, mut cells_tab: Grid, seed: u64, rng: &mut StdRng) -> Simulation {
+        // Un automate à une dimension part de la première ligne seule.
+        if self.rule.is_elementary() {
+            for y in 1..cells_tab.height() {
+                for x in 0..cells_tab.width() {
+                    cells_tab.set(x, y, Cell::DEAD);
                 }
             }
         }
-        living_neighbours
-    }
-
-    fn build_cells_with_density(&mut self) {
-        let mut count_cells = 0;
-        let mut rng = rand::thread_rng();
-        let mut cells_tab = Tab::default();
-
-        for x in 0..Self::SIZE {
-            for y in 0..Self::SIZE {
-                cells_tab.0[x][y] = Cell {
-                    living: rng.gen_bool(self.living_density as f64 / 100.0),
-                };
-                if cells_tab.0[x][y].living {
-                    count_cells += 1;
+        // Les cellules vivantes d'une variante multicolore sont réparties au
+        // hasard entre les couleurs, celles d'un automate continu entre les
+        // niveaux.
+        let levels = match self.rule.colours() {
+            _ if self.rule.is_continuous() => Some(self.rule.states() - 1),
+            0 => None,
+            colours => Some(colours),
+        };
+        if let Some(levels) = levels {
+            for y in 0..cells_tab.height() {
+                for x in 0..cells_tab.width() {
+                    if cells_tab.is_alive(x, y) {
+                        let state = rng.gen_range(1..=levels);
+                        cells_tab.set(x, y, Cell { state });
+                    }
                 }
             }
         }
-        self.nb_init_cells = count_cells;
-        self.number_of_living_cells = count_cells;
-        self.cells_tab = cells_tab;
-        self.initial_tab = cells_tab;
+        let mut simulation = Simulation::with_rule(cells_tab, self.rule);
+        simulation.set_neighbourhood(self.neighbourhood);
+        simulation.set_topology(self.topology);
+        simulation.set_lenia(self.lenia);
+        simulation.set_stochastic(self.stochastic);
+        simulation.set_seed(seed);
+        simulation.set_threads(self.threads);
+        simulation
+    }
+
+    fn build_cells_with_density(&mut self) {
+        let density = self.living_density as f64 / 100.0;
+        let seed = self.next_seed();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let cells_tab = Grid::random_with_density(self.width, self.height, density, &mut rng);
+        self.simulation = self.new_simulation(cells_tab, seed, &mut rng);
+        self.nb_init_cells = self.simulation.population();
+        self.initial_simulation = self.simulation.clone();
     }
 
     fn build_cells_with_number_of_cells(&mut self) {
-        let mut count_cells = 0;
-        let mut rng = rand::thread_rng();
-        self.cells_tab = Tab::default();
-        while count_cells < self.nb_init_cells {
-            let x = rng.gen_range(0..Self::SIZE);
-            let y = rng.gen_range(0..Self::SIZE);
-            if !self.cells_tab.0[x][y].living {
-                self.cells_tab.0[x][y].living = true;
-                count_cells += 1;
-            }
-        }
-        self.initial_tab = self.cells_tab;
+        let seed = self.next_seed();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let cells_tab = Grid::random_with_number_of_cells(
+            self.width,
+            self.height,
+            self.nb_init_cells,
+            &mut rng,
+        );
+        self.simulation = self.new_simulation(cells_tab, seed, &mut rng);
+        self.initial_simulation = self.simulation.clone();
     }
 
     fn réinitialiser(&mut self) {
-        self.cells_tab = self.initial_tab;
+        self.simulation = self.initial_simulation.clone();
+        self.simulation.set_rule(self.rule);
+        self.simulation.set_neighbourhood(self.neighbourhood);
+        self.simulation.set_topology(self.topology);
+        self.simulation.set_lenia(self.lenia);
+        self.simulation.set_stochastic(self.stochastic);
+        self.series = Series::default();
         self.playing = true;
-        self.generation = 1;
         self.screen = Screen::Simul;
-        self.number_of_living_cells = self.nb_init_cells;
-    }
-
-    fn default_button(x: usize, y: usize) -> Button<'static, Message> {
-        Button::new("")
-            .height(Length::Fill)
-            .width(Length::Fill)
-            .on_press(Message::ActiverDésactiver(x, y))
     }
 
     fn title(&self) -> String {
@@ -245,10 +402,12 @@ impl Conway {
             Screen::Conway => "Conway".into(),
             Screen::ExamplesC => "Charger un exemple".into(),
             Screen::SavesC => "Charger une sauvegarde".into(),
+            Screen::Census => "Jeu de Conway - Recensement".into(),
+            Screen::Search => "Jeu de Conway - Recherche".into(),
         }
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         let screen = match self.screen {
             Screen::Init => self.init(),
             Screen::Simul => self.simulation(),
@@ -256,18 +415,20 @@ impl Conway {
             Screen::Conway => self.conway(),
             Screen::ExamplesC => self.charge_examples(),
             Screen::SavesC => self.charger_saves(),
+            Screen::Census => self.recensement(),
+            Screen::Search => self.recherche(),
         };
         container(screen).into()
     }
 
-    fn charge_examples(&self) -> Element<Message> {
+    fn charge_examples(&self) -> Element<'_, Message> {
         let i_max = 1;
         let mut row_s = Row::new();
         for i in 0..i_max {
             let mut column_s = Column::new();
             for _j in 0..1 {
                 let image = Image::new(format!("examples/miniatures/miniature{}.png", i));
-                column_s = column_s.push(button(image).on_press(Message::ChargerSFinal(i)));
+                column_s = column_s.push(button(image).on_press(Message::ChargerEFinal(i)));
                 column_s = column_s.push(text("Gosper Glider Gun"));
             }
             row_s = row_s.push(column_s);
@@ -277,7 +438,7 @@ impl Conway {
         row_s.into()
     }
 
-    fn charger_saves(&self) -> Element<Message> {
+    fn charger_saves(&self) -> Element<'_, Message> {
         let i_max = self.nb_sauvegardes;
         let mut row_s = Row::new();
         for i in 0..i_max {
@@ -293,7 +454,146 @@ impl Conway {
         row_s.into()
     }
 
-    fn examples(&self) -> Element<Message> {
+    // Tableau des objets de la grille, les plus nombreux d'abord.
+    fn recensement(&self) -> Element<'_, Message> {
+        let buttons = row![
+            button("Exporter en CSV")
+                .on_press_maybe(self.census.is_ok().then_some(Message::ExporterRecensement))
+                .style(button::secondary),
+            button("Retour").on_press(Message::Simulation),
+        ]
+        .spacing(10);
+        let mut recensement = column![text("Recensement").size(35), buttons].spacing(10);
+        let census = match &self.census {
+            Ok(census) => census,
+            Err(e) => return recensement.push(text(e).size(20)).into(),
+        };
+        if self.simulation.cycle().is_none() {
+            recensement = recensement.push(text(
+                "La partie n'est pas encore stabilisée : les objets en formation sont inconnus.",
+            ));
+        }
+        recensement = recensement.push(text(format!(
+            "{} objets à la génération {}",
+            census.total(),
+            self.simulation.generation()
+        )));
+        let cell = |content: String| text(content).size(18).width(Length::FillPortion(1));
+        let mut table = Column::new().push(row![
+            cell("Code".into()),
+            cell("Nom".into()),
+            cell("Nature".into()),
+            cell("Nombre".into()),
+        ]);
+        for entry in census.entries() {
+            table = table.push(row![
+                cell(entry.code.clone()),
+                cell(entry.name.unwrap_or("").into()),
+                cell(entry.kind.to_string()),
+                cell(entry.count.to_string()),
+            ]);
+        }
+        recensement.push(scrollable(table.spacing(4))).into()
+    }
+
+    // Rectangle, période et déplacement à chercher sous la règle de la
+    // simulation, puis les motifs trouvés, chacun à charger dans la grille.
+    fn recherche(&self) -> Element<'_, Message> {
+        let labels = [
+            "Largeur",
+            "Hauteur",
+            "Période",
+            "Déplacement x",
+            "Déplacement y",
+        ];
+        let inputs = labels
+            .iter()
+            .zip(&self.input_search)
+            .enumerate()
+            .fold(Row::new(), |inputs, (i, (label, input))| {
+                inputs.push(text(*label).size(20)).push(
+                    text_input(label, input)
+                        .on_input(move |value| Message::InputSearch(i, value))
+                        .size(20),
+                )
+            })
+            .push(
+                Button::new("Chercher")
+                    .on_press_maybe((!self.searching).then_some(Message::Chercher)),
+            )
+            .spacing(10);
+        let mut recherche = column![
+            text("Recherche d'oscillateurs et de vaisseaux").size(35),
+            button("Retour").on_press(Message::Simulation),
+            text(format!(
+                "Règle {}, voisinage {} ; rectangle d'au plus {} cellules de côté, période d'au plus {}",
+                self.simulation.rule(),
+                self.simulation.neighbourhood(),
+                PeriodicSearch::MAX_SIZE,
+                PeriodicSearch::MAX_PERIOD
+            )),
+            inputs,
+        ]
+        .spacing(10);
+        let outcome = match &self.search {
+            None if self.searching => {
+                return recherche
+                    .push(text("Recherche en cours...").size(20))
+                    .into()
+            }
+            None => return recherche.into(),
+            Some(Err(e)) => return recherche.push(text(e).size(20)).into(),
+            Some(Ok(outcome)) => outcome,
+        };
+        recherche = recherche.push(text(if outcome.complete {
+            format!("{} motifs trouvés, recherche complète", outcome.found.len())
+        } else {
+            format!(
+                "{} motifs trouvés, recherche interrompue avant la fin",
+                outcome.found.len()
+            )
+        }));
+        let cell = |content: String| text(content).size(18).width(Length::FillPortion(2));
+        let mut table = Column::new().spacing(4);
+        for (i, found) in outcome.found.iter().enumerate() {
+            table = table.push(row![
+                cell(found.code.clone()),
+                cell(found.kind.to_string()),
+                cell(format!("{} cellules", found.cells.len())),
+                button("Charger")
+                    .on_press(Message::ChargerMotif(i))
+                    .style(button::secondary),
+            ]);
+        }
+        recherche.push(scrollable(table)).into()
+    }
+
+    // Recherche décrite par les champs.
+    fn periodic_search(&self) -> Result<PeriodicSearch, String> {
+        let [width, height, period, dx, dy] = &self.input_search;
+        let number = |input: &String| input.trim().parse::<usize>().ok();
+        let displacement = |input: &String| match input.trim() {
+            "" => Some(0),
+            input => input.parse::<i64>().ok(),
+        };
+        let (Some(width), Some(height), Some(period), Some(dx), Some(dy)) = (
+            number(width),
+            number(height),
+            number(period),
+            displacement(dx),
+            displacement(dy),
+        ) else {
+            return Err("Rentrez des nombres entiers!".to_string());
+        };
+        Ok(PeriodicSearch {
+            width,
+            height,
+            period,
+            displacement: (dx, dy),
+        })
+    }
+
+    fn examples(&self) -> Element<'_, Message> {
         column![
             button("Charger un exemple").on_press(Message::ChargerEScreen),
             button("Charger une sauvegarde").on_press(Message::ChargerSScreen),
@@ -303,7 +603,7 @@ impl Conway {
         .into()
     }
 
-    fn conway(&self) -> Element<Message> {
+    fn conway(&self) -> Element<'_, Message> {
         let images = self.create_frames();
         let path = PathBuf::from(&images[self.current_frame]);
         let handle = iced::widget::image::Handle::from(path);
@@ -326,7 +626,7 @@ impl Conway {
         .into()
     }
 
-    fn init(&self) -> Element<Message> {
+    fn init(&self) -> Element<'_, Message> {
         let mut init = Column::new();
         let mut density_button = button("Density Method").on_press(Message::FillingMethodChanged);
         let mut nb_cells_button =
@@ -347,11 +647,119 @@ impl Conway {
 
         init = init.push(Row::new());
 
+        let dimensions_row = row![
+            text(format!("{}<", Grid::MIN_SIZE)).size(20),
+            text_input("Largeur", self.input_w.as_str())
+                .on_input(Message::InputWidth)
+                .size(20),
+            text("x").size(20),
+            text_input("Hauteur", self.input_h.as_str())
+                .on_input(Message::InputHeight)
+                .size(20),
+            Button::new("OK").on_press(Message::ConvertDimensions),
+            text(format!("<{}", Grid::MAX_SIZE)).size(20),
+        ];
+        init = init.push(dimensions_row);
+        if !self.erreur_d {
+            init = init.push(row![text(format!(
+                "Dimensions de la grille: {0}x{1}",
+                self.width, self.height
+            ))]);
+        } else {
+            init = init.push(row![text("Rentrez des dimensions valides!")]);
+        }
+
+        let rule_row = row![
+            text("Règle").size(20),
+            text_input("B3/S23", self.input_r.as_str())
+                .on_input(Message::InputRule)
+                .size(20),
+            Button::new("OK").on_press(Message::ConvertRule),
+        ];
+        let presets_row = Rule::PRESETS
+            .iter()
+            .fold(Row::new(), |presets_row, (name, rulestring)| {
+                let preset: Rule = rulestring.parse().unwrap();
+                presets_row.push(button(*name).on_press(Message::ChooseRule(preset)).style(
+                    if preset == self.rule {
+                        button::primary
+                    } else {
+                        button::secondary
+                    },
+                ))
+            })
+            .spacing(10)
+            .wrap();
+        init = init.push(rule_row);
+        init = init.push(presets_row);
+        if !self.erreur_r {
+            init = init.push(row![text(format!("Règle validée: {0}", self.rule))]);
+        } else {
+            init = init.push(row![text("Rentrez une règle valide!")]);
+        }
+
+        if self.rule.is_continuous() {
+            init = init.push(self.lenia_settings());
+        }
+
+        init = init.push(self.stochastic_settings());
+
+        // Les règles isotropes n'existent que pour le voisinage de Moore.
+        let neighbourhood_row = Neighbourhood::ALL
+            .iter()
+            .fold(Row::new(), |neighbourhood_row, &neighbourhood| {
+                let chosen = match (neighbourhood, self.neighbourhood) {
+                    (Neighbourhood::Custom(_), Neighbourhood::Custom(_)) => true,
+                    _ => neighbourhood == self.neighbourhood,
+                };
+                neighbourhood_row.push(
+                    button(text(neighbourhood.to_string()))
+                        .on_press_maybe(
+                            (!chosen && neighbourhood.supports(&self.rule))
+                                .then_some(Message::ChooseNeighbourhood(neighbourhood)),
+                        )
+                        .style(if chosen {
+                            button::primary
+                        } else {
+                            button::secondary
+                        }),
+                )
+            })
+            .spacing(10)
+            .wrap();
+        init = init.push(row![text("Voisinage").size(20)]);
+        init = init.push(neighbourhood_row);
+        if let Neighbourhood::Custom(mask) = self.neighbourhood {
+            init = init.push(Self::mask_editor(mask));
+        }
+
+        let topology_row = Topology::ALL
+            .iter()
+            .fold(Row::new(), |topology_row, &topology| {
+                topology_row.push(
+                    button(text(topology.to_string()))
+                        .on_press_maybe(
+                            topology
+                                .supports(&self.rule)
+                                .then_some(Message::ChooseTopology(topology)),
+                        )
+                        .style(if topology == self.topology {
+                            button::primary
+                        } else {
+                            button::secondary
+                        }),
+                )
+            })
+            .spacing(10)
+            .wrap();
+        init = init.push(row![text("Bords de la grille").size(20)]);
+        init = init.push(topology_row);
+
         match self.filling_method {
             true => {
                 let vitesse_row = row![
                     text("1<").size(20),
-                    text_input("Choose the vitesse!", &self.input_v.as_str())
+                    text_input("Choose the vitesse!", self.input_v.as_str())
                         .on_input(Message::InputVitesse)
                         .size(20),
                     Button::new("OK").on_press(Message::ConvertVitesse),
@@ -360,7 +768,7 @@ impl Conway {
 
                 let fillingmethod_choice_row = row![
                     text("1%<").size(20),
-                    text_input("Choose the density of cells!", &self.input_c.as_str())
+                    text_input("Choose the density of cells!", self.input_c.as_str())
                         .on_input(Message::InputChangeMethod),
                     Button::new("OK").on_press(Message::ConvertDensity),
                     text("<100%").size(20)
@@ -368,22 +776,22 @@ impl Conway {
                 init = init.push(vitesse_row);
                 init = init.push(text("Don\'t worry, you can change it later.").size(15));
                 init = init.push(fillingmethod_choice_row);
-                if self.erreur_v == false {
+                if !self.erreur_v {
                     init = init.push(row![text(format!("Vitesse validée: {0}", self.vitesse))]);
                 }
-                if self.erreur_c == false {
+                if !self.erreur_c {
                     init = init.push(row![text(format!(
                         "Densité de cellules initiales: {0}",
                         self.living_density
                     ))])
                 };
                 init = init.push(button("Simulation").on_press(Message::Simulation));
-                return init.into();
+                init.into()
             }
             false => {
                 let vitesse_row = row![
                     text("1<").size(20),
-                    text_input("Choose the vitesse!", &self.input_v.as_str())
+                    text_input("Choose the vitesse!", self.input_v.as_str())
                         .on_input(Message::InputVitesse)
                         .size(20),
                     Button::new("OK").on_press(Message::ConvertVitesse),
@@ -392,61 +800,245 @@ impl Conway {
 
                 let fillingmethod_choice_row = row![
                     text("1<").size(20),
-                    text_input("Choose the number of cells!", &self.input_c.as_str())
+                    text_input("Choose the number of cells!", self.input_c.as_str())
                         .on_input(Message::InputChangeMethod),
                     Button::new("OK").on_press(Message::ConvertCells),
-                    text("<5000").size(20)
+                    text(format!("<{}", self.width * self.height)).size(20)
                 ];
                 init = init.push(vitesse_row);
                 init = init.push(text("Don\'t worry, you can change it later.").size(15));
                 init = init.push(fillingmethod_choice_row);
-                if self.erreur_v == false {
+                if !self.erreur_v {
                     init = init.push(row![text(format!("Vitesse validée: {0}", self.vitesse))]);
                 } else {
                     init = init.push(row![text("Rentrez un nombre valide!")]);
                 }
-                if self.erreur_c == false {
+                if !self.erreur_c {
                     init = init.push(row![text(format!(
                         "Nombres de cellules initiales: {0}",
-                        self.number_of_living_cells
+                        self.nb_init_cells
                     ))]);
                 } else {
                     init = init.push(row![text("Rentrez un nombre valide!")]);
                 }
                 init = init.push(button("Simulation").on_press(Message::Simulation));
-                return init.into();
+                init.into()
+            }
+        }
+    }
+
+    // Rayon de l'anneau, centre et largeur de la cloche de croissance, et pas
+    // de temps de Lenia.
+    fn lenia_settings(&self) -> Column<'_, Message> {
+        let labels = ["Rayon", "μ", "σ", "dt"];
+        let inputs = labels
+            .iter()
+            .zip(&self.input_lenia)
+            .enumerate()
+            .fold(Row::new(), |inputs, (i, (label, input))| {
+                inputs.push(text(*label).size(20)).push(
+                    text_input(label, input)
+                        .on_input(move |value| Message::InputLenia(i, value))
+                        .size(20),
+                )
+            })
+            .push(Button::new("OK").on_press(Message::ConvertLenia))
+            .spacing(10);
+        let status = if !self.erreur_l {
+            text(format!("Paramètres validés: {}", self.lenia))
+        } else {
+            text(format!(
+                "Rentrez des paramètres valides! (rayon de 1 à {}, μ entre 0 et 1, σ > 0, dt entre 0 et 1)",
+                Lenia::MAX_RADIUS
+            ))
+        };
+        column![text("Lenia").size(20), inputs, status]
+    }
+
+    // Probabilités de naissance et de survie, taux de bruit et graine.
+    fn stochastic_settings(&self) -> Column<'_, Message> {
+        let labels = ["Naissance (%)", "Survie (%)", "Bruit (%)", "Graine"];
+        let inputs = labels
+            .iter()
+            .zip(&self.input_stochastic)
+            .enumerate()
+            .fold(Row::new(), |inputs, (i, (label, input))| {
+                let placeholder = if i == 3 { "au hasard" } else { label };
+                inputs.push(text(*label).size(20)).push(
+                    text_input(placeholder, input)
+                        .on_input(move |value| Message::InputStochastic(i, value))
+                        .size(20),
+                )
+            })
+            .push(Button::new("OK").on_press(Message::ConvertStochastic))
+            .spacing(10);
+        let status = if self.erreur_s {
+            text("Rentrez des pourcentages entre 0 et 100 et une graine entière!")
+        } else {
+            let seed = self
+                .seed
+                .map_or("au hasard".to_string(), |seed| seed.to_string());
+            text(format!(
+                "Hasard validé: {}, graine {}",
+                self.stochastic, seed
+            ))
+        };
+        column![text("Hasard").size(20), inputs, status]
+    }
+
+    // Carré de cases à cocher autour de la cellule centrale, qui n'en fait
+    // jamais partie.
+    fn mask_editor(mask: Mask) -> Element<'static, Message> {
+        let range = -Mask::RADIUS..=Mask::RADIUS;
+        let mut editor = Column::new().spacing(2);
+        for dy in range.clone() {
+            let mut mask_row = Row::new().spacing(2);
+            for dx in range.clone() {
+                let centre = (dx, dy) == (0, 0);
+                mask_row = mask_row.push(
+                    button(text(if centre { "x" } else { "" }))
+                        .width(24)
+                        .height(24)
+                        .on_press_maybe((!centre).then_some(Message::ToggleMask(dx, dy)))
+                        .style(if mask.contains(dx, dy) {
+                            button::primary
+                        } else {
+                            button::secondary
+                        }),
+                );
             }
+            editor = editor.push(mask_row);
         }
+        editor.into()
     }
 
     fn create_miniature(&self) {
-        let mut img = RgbImage::new((Self::SIZE * 2) as u32, (Self::SIZE * 2) as u32);
-        for i in 0..Self::SIZE {
-            for j in 0..Self::SIZE {
-                if self.cells_tab.0[i][j].living {
-                    for di in 0..2 {
-                        for dj in 0..2 {
-                            img.put_pixel((2 * i + di) as u32, (2 * j + dj) as u32, Rgb([0, 0, 0]));
-                        }
-                    }
-                } else {
-                    for di in 0..2 {
-                        for dj in 0..2 {
-                            img.put_pixel(
-                                (2 * i + di) as u32,
-                                (2 * j + dj) as u32,
-                                Rgb([255, 255, 255]),
-                            );
-                        }
-                    }
-                }
+        // Deux pixels par cellule pour une grille de 50x50, moins au-delà.
+        let grid = self.simulation.grid();
+        let scale = 100.0 / grid.width().max(grid.height()) as f64;
+        self.grid_image(scale)
+            .save(format!(
+                "./saves/miniatures/miniature{}.png",
+                self.nb_sauvegardes
+            ))
+            .expect("Erreur lors de la sauvegarde de l'image");
+    }
+
+    // Image de la grille avec `scale` pixels par cellule.
+    fn grid_image(&self, scale: f64) -> RgbImage {
+        let grid = self.simulation.grid();
+        let colors = grid_view::palette(&self.simulation.rule());
+        let img_width = (grid.width() as f64 * scale).ceil() as u32;
+        let img_height = (grid.height() as f64 * scale).ceil() as u32;
+        let mut img = RgbImage::new(img_width, img_height);
+        for i in 0..img_width {
+            for j in 0..img_height {
+                let x = ((i as f64 / scale) as usize).min(grid.width() - 1);
+                let y = ((j as f64 / scale) as usize).min(grid.height() - 1);
+                let state = (grid.get(x, y).state as usize).min(colors.len() - 1);
+                img.put_pixel(i, j, Rgb(colors[state]));
             }
         }
-        img.save(format!(
-            "./saves/miniatures/miniature{}.png",
-            self.nb_sauvegardes
-        ))
-        .expect("Erreur lors de la sauvegarde de l'image");
+        img
+    }
+
+    // Exporte la grille, par exemple le diagramme espace-temps d'un automate
+    // à une dimension, avec des cellules d'au moins un pixel sur environ
+    // 1000 pixels de large.
+    fn export_png(&self) {
+        let grid = self.simulation.grid();
+        let scale = (1000 / grid.width().max(grid.height())).max(1);
+        let dossier = Path::new("./exports");
+        let numéro = fs::create_dir_all(dossier).and_then(|_| Self::compter_documents(dossier));
+        let file_name = match numéro {
+            Ok(n) => format!("./exports/export{}.png", n),
+            Err(e) => {
+                eprintln!("Erreur lors de l'accès au dossier {:?}: {}", dossier, e);
+                return;
+            }
+        };
+        if let Err(e) = self.grid_image(scale as f64).save(&file_name) {
+            eprintln!("Erreur lors de l'écriture de l'image {}: {}", file_name, e);
+        }
+    }
+
+    // Exporte les courbes : une ligne par génération calculée.
+    fn export_csv(&self) {
+        let dossier = Path::new("./exports");
+        let numéro = fs::create_dir_all(dossier).and_then(|_| Self::compter_documents(dossier));
+        let file_name = match numéro {
+            Ok(n) => format!("./exports/serie{}.csv", n),
+            Err(e) => {
+                eprintln!("Erreur lors de l'accès au dossier {:?}: {}", dossier, e);
+                return;
+            }
+        };
+        if let Err(e) = fs::write(&file_name, self.series.to_csv()) {
+            eprintln!("Erreur lors de l'écriture de la série {}: {}", file_name, e);
+        }
+    }
+
+    // Remplace la grille par un parent trouvé, à condition qu'elle n'ait
+    // pas changé pendant la recherche et qu'il y tienne ; Annuler redonne le
+    // motif.
+    fn load_parent(
+        &mut self,
+        search: &ParentSearch,
+        parent: Predecessor,
+    ) -> Result<Predecessor, String> {
+        if self.simulation.parent_search() != *search {
+            return Err("La grille a changé pendant la recherche d'un parent".to_string());
+        }
+        if let Predecessor::Parent(cells) = &parent {
+            let grid = self.simulation.grid();
+            let (w, h) = (grid.width() as i64, grid.height() as i64);
+            let fits = cells
+                .iter()
+                .all(|&(x, y)| (0..w).contains(&x) && (0..h).contains(&y));
+            if self.simulation.topology() != Topology::Unbounded && !fits {
+                return Err("Le parent trouvé dépasse de la grille".to_string());
+            }
+            self.simulation.replace_cells(cells);
+        }
+        Ok(parent)
+    }
+
+    fn parent_text(parent: &Result<Predecessor, String>) -> String {
+        match parent {
+            Ok(Predecessor::Parent(_)) => {
+                "Parent trouvé (Annuler pour revenir au motif)".to_string()
+            }
+            Ok(Predecessor::GardenOfEden) => "Jardin d'Eden : aucun parent possible".to_string(),
+            Ok(Predecessor::Unknown { interrupted: false }) => {
+                "Aucun parent trouvé près du motif".to_string()
+            }
+            Ok(Predecessor::Unknown { interrupted: true }) => {
+                "Recherche d'un parent interrompue".to_string()
+            }
+            Err(e) => e.clone(),
+        }
+    }
+
+    // Exporte le recensement : une ligne par sorte d'objet.
+    fn export_census(&self) {
+        let Ok(census) = &self.census else {
+            return;
+        };
+        let dossier = Path::new("./exports");
+        let numéro = fs::create_dir_all(dossier).and_then(|_| Self::compter_documents(dossier));
+        let file_name = match numéro {
+            Ok(n) => format!("./exports/recensement{}.csv", n),
+            Err(e) => {
+                eprintln!("Erreur lors de l'accès au dossier {:?}: {}", dossier, e);
+                return;
+            }
+        };
+        if let Err(e) = fs::write(&file_name, census.to_csv()) {
+            eprintln!(
+                "Erreur lors de l'écriture du recensement {}: {}",
+                file_name, e
+            );
+        }
     }
 
     fn compter_documents(dossier: &Path) -> Result<usize, std::io::Error> {
@@ -462,38 +1054,16 @@ impl Conway {
         Ok(nombre_documents)
     }
 
-    fn simulation(&self) -> Element<Message> {
-        let mut column_conway = Column::new();
-        for y in 0..Self::SIZE {
-            let mut row = Row::new();
-            for x in 0..Self::SIZE {
-                let living = self.cells_tab.0[x][y].living;
-                let grid_state = self.grid_state;
-                row = row.push(
-                    Self::default_button(x, y).style(move |_theme: &Theme, _status| {
-                        button::Style {
-                            background: Some(if living {
-                                Color::BLACK.into()
-                            } else {
-                                Color::WHITE.into()
-                            }),
-                            border: Border {
-                                color: Some(if grid_state {
-                                    color!(0xBFBFBF)
-                                } else {
-                                    Color::WHITE.into()
-                                })
-                                .unwrap(),
-                                width: 1.0,
-                                ..Border::default()
-                            },
-                            ..button::Style::default()
-                        }
-                    }),
-                );
-            }
-            column_conway = column_conway.push(row);
-        }
+    fn simulation(&self) -> Element<'_, Message> {
+        let grid_view = canvas(GridView {
+            grid: self.simulation.grid(),
+            rule: self.simulation.rule(),
+            grid_state: self.grid_state,
+            hexagonal: self.simulation.neighbourhood() == Neighbourhood::Hexagonal,
+        })
+        .width(Length::Fill)
+        .height(Length::Fill);
+
         let lecture_buttons = row![
             button("Update").on_press(Message::Update),
             if self.playing {
@@ -516,10 +1086,37 @@ impl Conway {
                 .style(button::secondary),
         ];
 
-        let settings_buttons = row![
+        let can_undo = self.simulation.can_undo();
+        let history_buttons = row![
+            button("Précédente")
+                .on_press_maybe(can_undo.then_some(Message::StepBack))
+                .style(button::secondary),
+            button("Annuler")
+                .on_press_maybe(can_undo.then_some(Message::Annuler))
+                .style(button::secondary),
+            button("Rétablir")
+                .on_press_maybe(self.simulation.can_redo().then_some(Message::Rétablir))
+                .style(button::secondary),
+        ];
+
+        let mut settings_buttons = row![
             button("Grille")
                 .on_press(Message::Grid)
                 .style(button::secondary),
+            button("Pause auto")
+                .on_press(Message::AutoPause)
+                .style(if self.auto_pause {
+                    button::primary
+                } else {
+                    button::secondary
+                }),
+            button("Courbes")
+                .on_press(Message::ToggleChart)
+                .style(if self.show_chart {
+                    button::primary
+                } else {
+                    button::secondary
+                }),
             button("Paramètres")
                 .on_press(Message::Settings)
                 .style(button::secondary),
@@ -529,10 +1126,36 @@ impl Conway {
             button("Sauvegarder")
                 .on_press(Message::Sauvegarder)
                 .style(button::secondary),
+            button("Exporter en PNG")
+                .on_press(Message::ExporterPng)
+                .style(button::secondary),
+            button("Recensement")
+                .on_press(Message::Recenser)
+                .style(button::secondary),
+            button("Recherche")
+                .on_press(Message::SearchScreen)
+                .style(button::secondary),
+            button("Trouver un parent")
+                .on_press_maybe(
+                    self.parent_search
+                        .is_none()
+                        .then_some(Message::TrouverParent)
+                )
+                .style(button::secondary),
             button("Menu principal")
                 .on_press(Message::Conway)
                 .style(button::secondary)
         ];
+        if self.simulation.topology() == Topology::Unbounded {
+            settings_buttons =
+                settings_buttons.push(button("Suivre").on_press(Message::Follow).style(
+                    if self.follow {
+                        button::primary
+                    } else {
+                        button::secondary
+                    },
+                ));
+        }
 
         let vitesse_buttons = row![
             button(">>")
@@ -549,81 +1172,265 @@ impl Conway {
                 .style(button::secondary),
         ];
 
-        let info_row = row![
+        let jump_buttons = row![
+            button("-")
+                .on_press(Message::DecreaseJump)
+                .style(button::secondary),
+            button(text(format!("Saut de 2^{}", self.jump_exponent))).on_press(Message::Jump),
+            button("+")
+                .on_press(Message::IncreaseJump)
+                .style(button::secondary),
+        ];
+
+        let mut info_row = row![
             text("Génération:").size(20),
-            text(self.generation.to_string()).size(21),
+            text(self.simulation.generation().to_string()).size(21),
             text("\t"),
             text("Cellules vivantes:").size(20),
-            text(self.number_of_living_cells.to_string()).size(21),
+            text(self.population_text()).size(21),
             text("\t"),
             text("Vitesse:").size(21),
             text(self.vitesse.to_string()).size(20),
+            text("\t"),
+            text("Règle:").size(20),
+            text(self.rule_text()).size(21),
+            text("\t"),
+            text("Voisinage:").size(20),
+            text(self.simulation.neighbourhood().to_string()).size(21),
+            text("\t"),
+            text("Topologie:").size(20),
+            text(self.simulation.topology().to_string()).size(21),
         ];
+        if let Some(cycle) = self.simulation.cycle() {
+            info_row = info_row.push(text("\t"));
+            info_row = info_row.push(text("Cycle:").size(20));
+            info_row = info_row.push(text(cycle.to_string()).size(21));
+        }
+        info_row = info_row.push(text("\t"));
+        info_row = info_row.push(text("Graine:").size(20));
+        info_row = info_row.push(text(self.simulation.seed().to_string()).size(21));
+        if self.simulation.topology() == Topology::Unbounded {
+            let (x0, y0) = self.simulation.view_origin();
+            info_row = info_row.push(text("\t"));
+            info_row = info_row.push(text("Vue:").size(20));
+            info_row = info_row.push(text(format!("({}, {})", x0, y0)).size(21));
+        }
+        if self.parent_search.is_some() {
+            info_row = info_row.push(text("\t"));
+            info_row = info_row.push(text("Recherche d'un parent...").size(20));
+        }
+        if let Some(parent) = &self.parent {
+            info_row = info_row.push(text("\t"));
+            info_row = info_row.push(text(Self::parent_text(parent)).size(20));
+        }
 
-        let control_row = row![lecture_buttons, vitesse_buttons, settings_buttons].spacing(120);
-        column![column_conway, control_row, info_row,].into()
-    }
-
-    fn update_cells(&mut self) {
-        let mut next_cells_tab = self.cells_tab;
-        for x in 0..Self::SIZE {
-            for y in 0..Self::SIZE {
-                let living_neighbours = self.check_neighbours(x, y);
-
-                next_cells_tab.0[x][y].living =
-                    match (self.cells_tab.0[x][y].living, living_neighbours) {
-                        (true, 2) | (true, 3) => true, // Reste en vie si 2 ou 3 voisins vivants
-                        (false, 3) => {
-                            self.number_of_living_cells += 1;
-                            true
-                        } // Devient vivant si exactement 3 voisins vivants
-                        (true, _) => {
-                            self.number_of_living_cells -= 1;
-                            false
-                        }
-                        _ => false, // Sinon, reste ou devient mort
-                    };
-            }
+        let control_row = row![
+            lecture_buttons,
+            history_buttons,
+            jump_buttons,
+            vitesse_buttons,
+            settings_buttons
+        ]
+        .spacing(60);
+        let mut simulation = column![grid_view, control_row];
+        if let Some(names) = grid_view::state_names(&self.simulation.rule()) {
+            simulation = simulation.push(self.brush_row(names));
+        }
+        simulation = simulation.push(info_row);
+        if self.show_chart {
+            simulation = simulation.push(self.chart());
         }
-        self.cells_tab = next_cells_tab;
+        simulation.into()
+    }
+
+    // Courbes de population (noir), naissances (vert) et morts (rouge), avec
+    // le choix du nombre de générations montrées.
+    fn chart(&self) -> Column<'_, Message> {
+        let chart = canvas(Chart {
+            series: &self.series,
+            window: self.chart_window,
+        })
+        .width(Length::Fill)
+        .height(160);
+        let buttons = chart::WINDOWS
+            .iter()
+            .fold(Row::new(), |buttons, &window| {
+                let label = window.map_or("Tout".to_string(), |n| format!("{} générations", n));
+                buttons.push(
+                    button(text(label))
+                        .on_press(Message::ChooseChartWindow(window))
+                        .style(if window == self.chart_window {
+                            button::primary
+                        } else {
+                            button::secondary
+                        }),
+                )
+            })
+            .push(
+                button("Exporter en CSV")
+                    .on_press(Message::ExporterCsv)
+                    .style(button::secondary),
+            )
+            .spacing(10);
+        column![buttons, chart]
+    }
+
+    // Règle, suivie des paramètres d'un automate continu ou du hasard ajouté.
+    fn rule_text(&self) -> String {
+        let rule = self.simulation.rule();
+        let stochastic = self.simulation.stochastic();
+        if rule.is_continuous() {
+            format!("{} ({})", rule, self.simulation.lenia())
+        } else if !stochastic.is_deterministic() && !rule.is_elementary() {
+            format!("{} ({})", rule, stochastic)
+        } else {
+            rule.to_string()
+        }
+    }
+
+    // Nombre de cellules vivantes, suivi de celui de chaque couleur pour une
+    // variante multicolore.
+    fn population_text(&self) -> String {
+        let population = self.simulation.population().to_string();
+        let rule = self.simulation.rule();
+        let Some(names) = grid_view::state_names(&rule).filter(|_| rule.colours() > 0) else {
+            return population;
+        };
+        let colours: Vec<String> = names[1..]
+            .iter()
+            .zip(self.simulation.colour_populations())
+            .map(|(name, count)| format!("{} {}", name, count))
+            .collect();
+        format!("{} ({})", population, colours.join(", "))
+    }
+
+    // Choix de l'état peint d'un clic, chaque bouton aux couleurs de son état.
+    fn brush_row(&self, names: &'static [&'static str]) -> Row<'_, Message> {
+        let colors = grid_view::palette(&self.simulation.rule());
+        names
+            .iter()
+            .zip(colors)
+            .enumerate()
+            .fold(Row::new(), |brush_row, (state, (name, [r, g, b]))| {
+                let chosen = state as u8 == self.brush;
+                let light = r as u32 + g as u32 + b as u32 > 384;
+                brush_row.push(
+                    button(text(*name))
+                        .on_press(Message::ChooseBrush(state as u8))
+                        .style(move |theme, status| button::Style {
+                            background: Some(Color::from_rgb8(r, g, b).into()),
+                            text_color: if light { Color::BLACK } else { Color::WHITE },
+                            border: Border {
+                                color: Color::BLACK,
+                                width: if chosen { 3.0 } else { 1.0 },
+                                radius: 4.0.into(),
+                            },
+                            ..button::primary(theme, status)
+                        }),
+                )
+            })
+            .spacing(10)
+    }
+
+    // Sans HashLife, chaque génération du saut est calculée : on reste sur
+    // des sauts courts pour ne pas bloquer l'interface.
+    fn max_jump_exponent(&self) -> u8 {
+        if self.simulation.hashlife_enabled() {
+            30
+        } else {
+            10
+        }
+    }
+
+    fn charger(&mut self, file_name: String) {
+        let mut contents = String::new();
+        let read = File::open(&file_name).and_then(|mut file| file.read_to_string(&mut contents));
+        if let Err(e) = read {
+            eprintln!("Erreur lors de la lecture du fichier {}: {}", file_name, e);
+            return;
+        }
+        let mut deserialized: Simulation = match serde_json::from_str(&contents) {
+            Ok(simulation) => simulation,
+            Err(e) => {
+                eprintln!("Sauvegarde illisible {}: {}", file_name, e);
+                return;
+            }
+        };
+        deserialized.restart();
+        deserialized.set_threads(self.threads);
+        let rule = deserialized.rule();
+        let (width, height) = (deserialized.grid().width(), deserialized.grid().height());
+        *self = Conway {
+            screen: Screen::Simul,
+            nb_init_cells: deserialized.population(),
+            initial_simulation: deserialized.clone(),
+            rule,
+            neighbourhood: deserialized.neighbourhood(),
+            topology: deserialized.topology(),
+            lenia: deserialized.lenia(),
+            stochastic: deserialized.stochastic(),
+            input_r: rule.to_string(),
+            input_lenia: Self::lenia_inputs(deserialized.lenia()),
+            input_stochastic: Self::stochastic_inputs(deserialized.stochastic(), None),
+            simulation: deserialized,
+            ..Self::new(width, height, self.threads)
+        };
     }
 
-    fn update(&mut self, message: Message) {
+    fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::Update => {
-                Self::update_cells(self);
-                self.generation += 1;
+                let known = self.simulation.cycle().is_some();
+                self.simulation.step();
+                self.parent = None;
+                self.series.record(&self.simulation);
+                if self.auto_pause && !known && self.simulation.cycle().is_some() {
+                    self.playing = false;
+                }
+                if self.follow {
+                    self.simulation.center_view();
+                }
             }
-            Message::PlayPause => self.playing = !self.playing,
-            Message::InitCellsNumber(value) => {
-                self.nb_init_cells = value;
-                self.number_of_living_cells = value
+            Message::StepBack => {
+                self.simulation.step_back();
+                self.series.forget_after(self.simulation.generation());
+            }
+            Message::Annuler => {
+                self.simulation.undo();
+                self.series.forget_after(self.simulation.generation());
+                self.parent = None;
+            }
+            Message::Rétablir => {
+                self.simulation.redo();
+                self.series.record(&self.simulation);
             }
-            Message::InitDensity(value) => self.living_density = value,
+            Message::PlayPause => self.playing = !self.playing,
             Message::FillingMethodChanged => {
                 self.filling_method = !self.filling_method;
             }
             Message::Simulation => match self.screen {
                 Screen::Conway => {
-                    let cells_tab = Tab::default();
+                    let seed = self.next_seed();
+                    let simulation = self.new_simulation(
+                        Grid::new(self.width, self.height),
+                        seed,
+                        &mut StdRng::seed_from_u64(seed),
+                    );
                     *self = Conway {
-                        cells_tab,
-                        playing: false,
-                        generation: 1,
+                        initial_simulation: simulation.clone(),
+                        simulation,
                         screen: Screen::Simul,
-                        nb_init_cells: 0,
                         living_density: 0,
-                        filling_method: true,
-                        number_of_living_cells: 0,
-                        initial_tab: (cells_tab),
-                        vitesse: 100,
-                        grid_state: true,
-                        input_c: "".to_string(),
-                        input_v: "".to_string(),
-                        erreur_c: true,
-                        erreur_v: true,
-                        nb_sauvegardes: Self::compter_documents(Path::new("./saves/main")).unwrap(),
-                        current_frame: 0,
+                        rule: self.rule,
+                        neighbourhood: self.neighbourhood,
+                        topology: self.topology,
+                        lenia: self.lenia,
+                        stochastic: self.stochastic,
+                        seed: self.seed,
+                        input_r: self.rule.to_string(),
+                        input_lenia: Self::lenia_inputs(self.lenia),
+                        input_stochastic: self.input_stochastic.clone(),
+                        ..Self::new(self.width, self.height, self.threads)
                     }
                 }
                 Screen::Init => {
@@ -631,55 +1438,77 @@ impl Conway {
                         true => Self::build_cells_with_density(self),
                         false => Self::build_cells_with_number_of_cells(self),
                     };
+                    self.series = Series::default();
                     self.screen = Screen::Simul;
                 }
                 Screen::Example => self.screen = Screen::Simul,
                 Screen::Simul => (),
                 Screen::SavesC => (),
                 Screen::ExamplesC => (),
+                Screen::Census | Screen::Search => self.screen = Screen::Simul,
             },
             Message::Settings => self.screen = Screen::Init,
             Message::Réinitialiser => {
                 Self::réinitialiser(self);
                 self.playing = false;
-                self.generation = 1;
             }
 
             Message::ActiverDésactiver(x, y) => {
-                if self.cells_tab.0[x][y].living {
-                    self.number_of_living_cells -= 1;
-                    self.cells_tab.0[x][y].living = false;
+                if grid_view::state_names(&self.simulation.rule()).is_some() {
+                    let cell = Cell { state: self.brush };
+                    self.simulation.paint(x, y, cell);
                 } else {
-                    self.number_of_living_cells += 1;
-                    self.cells_tab.0[x][y].living = true;
+                    self.simulation.toggle(x, y);
                 }
             }
-            Message::ChangeVitesse(valeur) => self.vitesse = valeur,
+            Message::ChooseBrush(state) => self.brush = state,
             Message::Grid => self.grid_state = !self.grid_state,
+            Message::AutoPause => self.auto_pause = !self.auto_pause,
+            Message::ToggleChart => self.show_chart = !self.show_chart,
+            Message::ChooseChartWindow(window) => self.chart_window = window,
+            Message::Follow => {
+                self.follow = !self.follow;
+                if self.follow {
+                    self.simulation.center_view();
+                }
+            }
+            Message::Jump => {
+                self.jump_exponent = self.jump_exponent.min(self.max_jump_exponent());
+                self.simulation.jump(self.jump_exponent);
+                self.series.record(&self.simulation);
+                self.parent = None;
+                if self.follow {
+                    self.simulation.center_view();
+                }
+            }
+            Message::IncreaseJump => {
+                self.jump_exponent = (self.jump_exponent + 1).min(self.max_jump_exponent());
+            }
+            Message::DecreaseJump => self.jump_exponent = self.jump_exponent.saturating_sub(1),
             Message::IncreaseVitesse => {
                 if self.vitesse >= 5 {
-                    self.vitesse = self.vitesse + 5;
+                    self.vitesse += 5;
                 } else {
                     self.vitesse = 0;
                 }
             }
             Message::DecreaseVitesse => {
                 if self.vitesse <= 150 {
-                    self.vitesse = self.vitesse - 5;
+                    self.vitesse -= 5;
                 } else {
                     self.vitesse = 150;
                 }
             }
             Message::IncreaseQuickVitesse => {
                 if self.vitesse >= 25 {
-                    self.vitesse = self.vitesse + 25;
+                    self.vitesse += 25;
                 } else {
                     self.vitesse = 0;
                 }
             }
             Message::DecreaseQuickVitesse => {
                 if self.vitesse <= 1500 {
-                    self.vitesse = self.vitesse - 25;
+                    self.vitesse -= 25;
                 } else {
                     self.vitesse = 150;
                 }
@@ -694,7 +1523,7 @@ impl Conway {
 
             Message::ConvertVitesse => {
                 let n = self.input_v.parse().unwrap();
-                if n >= 1 && n <= 500 {
+                if (1..=500).contains(&n) {
                     self.vitesse = n;
                     self.erreur_v = false;
                 } else {
@@ -709,7 +1538,7 @@ impl Conway {
             }
             Message::ConvertDensity => {
                 let n = self.input_c.parse().unwrap();
-                if n >= 1 && n <= 100 {
+                if (1..=100).contains(&n) {
                     self.living_density = n;
                     self.erreur_c = false;
                 } else {
@@ -720,20 +1549,114 @@ impl Conway {
 
             Message::ConvertCells => {
                 let n = self.input_c.parse().unwrap();
-                if n >= 1 && n <= 5000 {
-                    self.number_of_living_cells = n;
+                if (1..=(self.width * self.height) as u32).contains(&n) {
+                    self.nb_init_cells = n;
                     self.erreur_c = false;
                 } else {
                     self.input_c = "".to_string();
                     self.erreur_c = true;
                 }
             }
+            Message::InputWidth(n) => {
+                if n.chars().all(|c| c.is_ascii_digit()) {
+                    self.input_w = n;
+                }
+            }
+            Message::InputHeight(n) => {
+                if n.chars().all(|c| c.is_ascii_digit()) {
+                    self.input_h = n;
+                }
+            }
+            Message::ConvertDimensions => match (self.input_w.parse(), self.input_h.parse()) {
+                (Ok(width), Ok(height))
+                    if Grid::is_valid_size(width) && Grid::is_valid_size(height) =>
+                {
+                    self.width = width;
+                    self.height = height;
+                    self.erreur_d = false;
+                }
+                _ => self.erreur_d = true,
+            },
+            Message::InputRule(r) => self.input_r = r,
+            Message::ConvertRule => match self.input_r.parse() {
+                Ok(rule) => return self.update(Message::ChooseRule(rule)),
+                Err(_) => self.erreur_r = true,
+            },
+            Message::ChooseRule(rule) => {
+                self.rule = rule;
+                self.simulation.set_rule(rule);
+                self.input_r = rule.to_string();
+                self.erreur_r = false;
+                if self.brush >= rule.states() {
+                    self.brush = 1;
+                }
+                if !self.topology.supports(&rule) {
+                    self.choose_topology(Topology::default());
+                }
+                if !self.neighbourhood.supports(&rule) {
+                    self.choose_neighbourhood(Neighbourhood::Moore);
+                }
+            }
+            Message::InputLenia(i, value) => self.input_lenia[i] = value,
+            Message::ConvertLenia => {
+                let [radius, mu, sigma, dt] = &self.input_lenia;
+                let lenia = match (radius.parse(), mu.parse(), sigma.parse(), dt.parse()) {
+                    (Ok(radius), Ok(mu), Ok(sigma), Ok(dt)) => Some(Lenia {
+                        radius,
+                        mu,
+                        sigma,
+                        dt,
+                    }),
+                    _ => None,
+                };
+                match lenia.filter(Lenia::is_valid) {
+                    Some(lenia) => {
+                        self.lenia = lenia;
+                        self.simulation.set_lenia(lenia);
+                        self.erreur_l = false;
+                    }
+                    None => self.erreur_l = true,
+                }
+            }
+            Message::InputStochastic(i, value) => self.input_stochastic[i] = value,
+            Message::ConvertStochastic => {
+                let [birth, survival, noise, seed] = &self.input_stochastic;
+                let percent = |input: &String| input.parse::<f64>().map(|p| p / 100.0);
+                let seed = match seed.trim() {
+                    "" => Ok(None),
+                    seed => seed.parse().map(Some),
+                };
+                let stochastic = match (percent(birth), percent(survival), percent(noise)) {
+                    (Ok(birth), Ok(survival), Ok(noise)) => Some(Stochastic {
+                        birth,
+                        survival,
+                        noise,
+                    }),
+                    _ => None,
+                };
+                match (stochastic.filter(Stochastic::is_valid), seed) {
+                    (Some(stochastic), Ok(seed)) => {
+                        self.stochastic = stochastic;
+                        self.seed = seed;
+                        self.simulation.set_stochastic(stochastic);
+                        self.erreur_s = false;
+                    }
+                    _ => self.erreur_s = true,
+                }
+            }
+            Message::ChooseNeighbourhood(neighbourhood) => self.choose_neighbourhood(neighbourhood),
+            Message::ToggleMask(dx, dy) => {
+                if let Neighbourhood::Custom(mask) = self.neighbourhood {
+                    self.choose_neighbourhood(Neighbourhood::Custom(mask.toggled(dx, dy)));
+                }
+            }
+            Message::ChooseTopology(topology) => self.choose_topology(topology),
             Message::Sauvegarder => {
                 let serialized = match serde_json::to_string(&self) {
                     Ok(s) => s,
                     Err(e) => {
                         eprintln!("Erreur lors de la sérialisation : {}", e);
-                        return;
+                        return Task::none();
                     }
                 };
                 Self::create_miniature(self);
@@ -744,7 +1667,7 @@ impl Conway {
                     Ok(f) => f,
                     Err(e) => {
                         eprintln!("Erreur lors de la création du fichier {}: {}", file_name, e);
-                        return;
+                        return Task::none();
                     }
                 };
 
@@ -755,107 +1678,151 @@ impl Conway {
                     );
                 };
             }
+            Message::ExporterPng => self.export_png(),
+            Message::ExporterCsv => self.export_csv(),
+            Message::Recenser => {
+                self.census = self.simulation.census();
+                self.playing = false;
+                self.screen = Screen::Census;
+            }
+            Message::ExporterRecensement => self.export_census(),
+            Message::SearchScreen => {
+                self.playing = false;
+                self.screen = Screen::Search;
+            }
+            Message::InputSearch(i, value) => self.input_search[i] = value,
+            // La recherche se fait sous la règle et le voisinage de la
+            // simulation au moment où elle est lancée.
+            Message::Chercher => match self.periodic_search() {
+                Ok(search) => {
+                    let rule = self.simulation.rule();
+                    let neighbourhood = self.simulation.neighbourhood();
+                    self.searching = true;
+                    self.search = None;
+                    return in_background(
+                        move || search.run(&rule, neighbourhood),
+                        Message::RechercheFinie,
+                    );
+                }
+                Err(e) => self.search = Some(Err(e)),
+            },
+            Message::RechercheFinie(outcome) => {
+                self.searching = false;
+                self.search = Some(outcome);
+            }
+            Message::ChargerMotif(i) => {
+                if let Some(Ok(outcome)) = &self.search {
+                    self.simulation.load_pattern(&outcome.found[i].cells);
+                    self.screen = Screen::Simul;
+                }
+            }
+            Message::TrouverParent => {
+                let search = self.simulation.parent_search();
+                self.playing = false;
+                self.parent = None;
+                self.parent_search = Some(search.clone());
+                return in_background(move || search.run(), Message::ParentTrouve);
+            }
+            Message::ParentTrouve(parent) => {
+                if let Some(search) = self.parent_search.take() {
+                    self.parent = Some(parent.and_then(|parent| self.load_parent(&search, parent)));
+                }
+            }
             Message::ChargerSScreen => self.screen = Screen::SavesC,
             Message::ChargerSFinal(i) => {
-                let mut file = File::open(format!("./saves/main/Sauvegarde{}.txt", i))
-                    .expect("Unable to open file");
-                let mut contents = String::new();
-                file.read_to_string(&mut contents)
-                    .expect("Unable to read file");
-                let deserialized: Conway = serde_json::from_str(&contents).unwrap();
-                *self = Conway {
-                    cells_tab: deserialized.cells_tab,
-                    playing: false,
-                    generation: 1,
-                    screen: Screen::Simul,
-                    nb_init_cells: 0,
-                    living_density: 0,
-                    filling_method: true,
-                    number_of_living_cells: 0,
-                    initial_tab: (deserialized.initial_tab),
-                    vitesse: 100,
-                    grid_state: true,
-                    input_c: "".to_string(),
-                    input_v: "".to_string(),
-                    erreur_c: true,
-                    erreur_v: true,
-                    nb_sauvegardes: Self::compter_documents(Path::new("./saves/main")).unwrap(),
-                    current_frame: 0,
-                }
+                Self::charger(self, format!("./saves/main/Sauvegarde{}.txt", i))
             }
             Message::ChargerEScreen => self.screen = Screen::ExamplesC,
             Message::ChargerEFinal(i) => {
-                let mut file = File::open(format!("./saves/main/Sauvegarde{}.txt", i))
-                    .expect("Unable to open file");
-                let mut contents = String::new();
-                file.read_to_string(&mut contents)
-                    .expect("Unable to read file");
-                let deserialized: Conway = serde_json::from_str(&contents).unwrap();
-                *self = Conway {
-                    cells_tab: deserialized.cells_tab,
-                    playing: false,
-                    generation: 1,
-                    screen: Screen::Simul,
-                    nb_init_cells: 0,
-                    living_density: 0,
-                    filling_method: true,
-                    number_of_living_cells: 0,
-                    initial_tab: (deserialized.initial_tab),
-                    vitesse: 100,
-                    grid_state: true,
-                    input_c: "".to_string(),
-                    input_v: "".to_string(),
-                    erreur_c: true,
-                    erreur_v: true,
-                    nb_sauvegardes: Self::compter_documents(Path::new("./saves/main")).unwrap(),
-                    current_frame: 0,
-                }
+                Self::charger(self, format!("./examples/main/example{}.txt", i))
             }
             Message::Tick => {
                 self.current_frame = (self.current_frame + 1) % (Self::create_frames(self).len())
             }
         }
+        Task::none()
     }
 }
-impl Default for Tab {
-    fn default() -> Self {
-        Tab([[Cell { living: false }; Conway::SIZE]; Conway::SIZE])
+impl Conway {
+    fn choose_neighbourhood(&mut self, neighbourhood: Neighbourhood) {
+        self.neighbourhood = neighbourhood;
+        self.simulation.set_neighbourhood(neighbourhood);
     }
-}
 
-impl Default for Conway {
-    fn default() -> Self {
-        let mut count_cells = 0;
-        let density = 25;
-        let mut rng = rand::thread_rng();
-        let mut cells_tab = Tab::default();
-        for x in 0..Self::SIZE {
-            for y in 0..Self::SIZE {
-                cells_tab.0[x][y] = Cell {
-                    living: rng.gen_bool(density as f64 / 100.0),
-                };
-                if cells_tab.0[x][y].living {
-                    count_cells += 1;
-                }
-            }
-        }
+    fn choose_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+        self.simulation.set_topology(topology);
+    }
+
+    fn lenia_inputs(lenia: Lenia) -> [String; 4] {
+        [
+            lenia.radius.to_string(),
+            lenia.mu.to_string(),
+            lenia.sigma.to_string(),
+            lenia.dt.to_string(),
+        ]
+    }
+
+    // Probabilités en pourcentages, puis la graine, vide si elle est tirée au
+    // hasard.
+    fn stochastic_inputs(stochastic: Stochastic, seed: Option<u64>) -> [String; 4] {
+        [
+            (stochastic.birth * 100.0).to_string(),
+            (stochastic.survival * 100.0).to_string(),
+            (stochastic.noise * 100.0).to_string(),
+            seed.map_or(String::new(), |seed| seed.to_string()),
+        ]
+    }
+
+    fn new(width: usize, height: usize, threads: usize) -> Self {
+        let mut simulation = Simulation::new(Grid::new(width, height));
+        simulation.set_threads(threads);
 
         Self {
-            cells_tab,
+            initial_simulation: simulation.clone(),
+            simulation,
             playing: false,
-            generation: 1,
             screen: Screen::Conway,
-            nb_init_cells: count_cells,
+            nb_init_cells: 0,
             living_density: 25,
             filling_method: true,
-            number_of_living_cells: count_cells,
-            initial_tab: cells_tab,
+            width,
+            height,
+            threads,
+            rule: Rule::default(),
+            neighbourhood: Neighbourhood::default(),
+            topology: Topology::default(),
+            lenia: Lenia::default(),
+            stochastic: Stochastic::default(),
+            seed: None,
             vitesse: 100,
             grid_state: true,
+            auto_pause: false,
+            series: Series::default(),
+            show_chart: false,
+            chart_window: Some(1000),
+            census: Ok(Census::default()),
+            input_search: ["4", "4", "2", "0", "0"].map(String::from),
+            searching: false,
+            search: None,
+            parent_search: None,
+            parent: None,
+            follow: true,
+            jump_exponent: 10,
+            brush: 1,
             input_c: "".to_string(),
             input_v: "".to_string(),
+            input_w: width.to_string(),
+            input_h: height.to_string(),
+            input_r: Rule::default().to_string(),
+            input_lenia: Self::lenia_inputs(Lenia::default()),
+            input_stochastic: Self::stochastic_inputs(Stochastic::default(), None),
             erreur_c: true,
             erreur_v: true,
+            erreur_d: false,
+            erreur_r: false,
+            erreur_l: false,
+            erreur_s: false,
             nb_sauvegardes: Self::compter_documents(Path::new("./saves/main")).unwrap(),
             current_frame: 0,
         }
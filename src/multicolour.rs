@@ -0,0 +1,76 @@
+use crate::bands::map_bands;
+use crate::grid::Grid;
+use crate::neighbourhood::Neighbourhood;
+use crate::rule::Rule;
+use crate::topology::Topology;
+
+// Les variantes multicolores ont au plus quatre couleurs (QuadLife).
+const MAX_COLOURS: usize = 4;
+
+// Couleur d'une cellule qui naît, `counts[c]` étant le nombre de ses voisines
+// de couleur c + 1 : la couleur majoritaire ou, en cas d'égalité, la moins
+// représentée (la première en cas de nouvelle égalité). Quand ses trois
+// parents ont des couleurs différentes, une cellule de QuadLife prend ainsi
+// la quatrième.
+fn birth_colour(counts: &[u32]) -> u8 {
+    let max = counts.iter().max().copied().unwrap_or(0);
+    let colour = if counts.iter().filter(|&&count| count == max).count() == 1 {
+        counts.iter().position(|&count| count == max)
+    } else {
+        let min = counts.iter().min().copied().unwrap_or(0);
+        counts.iter().position(|&count| count == min)
+    };
+    colour.unwrap_or(0) as u8 + 1
+}
+
+// Génération suivante d'une variante multicolore ; renvoie la grille et son
+// nombre de cellules vivantes. La règle sans les couleurs décide qui vit et
+// qui naît, toutes les couleurs comptant comme vivantes ; une cellule
+// vivante garde sa couleur.
+pub(crate) fn step(
+    grid: &Grid,
+    rule: &Rule,
+    neighbourhood: Neighbourhood,
+    topology: Topology,
+    threads: usize,
+) -> (Grid, u32) {
+    let colours = (rule.colours() as usize).min(MAX_COLOURS);
+    let base = rule.uncoloured();
+    let offsets = [neighbourhood.offsets(false), neighbourhood.offsets(true)];
+    let (width, height) = (grid.width(), grid.height());
+
+    let mut next = grid.clone();
+    let populations = map_bands(next.cells_mut(), width, threads, |first_row, band| {
+        let mut population = 0;
+        for (dy, row) in band.chunks_mut(width).enumerate() {
+            let y = first_row + dy;
+            for (x, cell) in row.iter_mut().enumerate() {
+                let mut living = 0;
+                let mut counts = [0; MAX_COLOURS];
+                for (i, &(dx, dy)) in offsets[y % 2].iter().enumerate() {
+                    let neighbour =
+                        topology.resolve(x as isize + dx, y as isize + dy, width, height);
+                    if let Some((nx, ny)) = neighbour {
+                        let state = grid.get(nx, ny).state as usize;
+                        if (1..=colours).contains(&state) {
+                            living |= 1 << i;
+                            counts[state - 1] += 1;
+                        }
+                    }
+                }
+                let alive = rule.is_alive(cell.state);
+                let lives = neighbourhood.next_state(&base, alive as u8, living) == 1;
+                cell.state = match (alive, lives) {
+                    (true, true) => cell.state,
+                    (false, true) => birth_colour(&counts[..colours]),
+                    _ => 0,
+                };
+                if cell.state != 0 {
+                    population += 1;
+                }
+            }
+        }
+        population
+    });
+    (next, populations.into_iter().sum())
+}
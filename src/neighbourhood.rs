@@ -0,0 +1,107 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rule::{Rule, MOORE_NEIGHBOURS};
+
+// Cellules dont l'état compte pour l'évolution d'une cellule.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Neighbourhood {
+    // Les huit cellules qui l'entourent.
+    #[default]
+    Moore,
+    // Les quatre cellules qui partagent un côté avec elle.
+    VonNeumann,
+    // Les six cellules voisines d'un pavage hexagonal : les lignes impaires
+    // sont décalées d'une demi-cellule vers la droite.
+    Hexagonal,
+    // Les cellules choisies dans le carré de côté 5 centré sur elle.
+    Custom(Mask),
+}
+
+// Cellules retenues dans le carré de côté 5 centré sur une cellule : le bit
+// (dy + 2) * 5 + dx + 2 correspond au décalage (dx, dy). Le centre n'en fait
+// jamais partie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Mask(u32);
+
+impl Mask {
+    pub const RADIUS: isize = 2;
+    pub const MOORE: Mask = Mask(0b111 << 6 | 0b101 << 11 | 0b111 << 16);
+
+    fn bit(dx: isize, dy: isize) -> u32 {
+        ((dy + Self::RADIUS) * (2 * Self::RADIUS + 1) + dx + Self::RADIUS) as u32
+    }
+
+    pub fn contains(&self, dx: isize, dy: isize) -> bool {
+        self.0 >> Self::bit(dx, dy) & 1 == 1
+    }
+
+    pub fn toggled(self, dx: isize, dy: isize) -> Mask {
+        if (dx, dy) == (0, 0) {
+            return self;
+        }
+        Mask(self.0 ^ 1 << Self::bit(dx, dy))
+    }
+
+    fn offsets(&self) -> Vec<(isize, isize)> {
+        let range = -Self::RADIUS..=Self::RADIUS;
+        range
+            .clone()
+            .flat_map(|dy| range.clone().map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| self.contains(dx, dy))
+            .collect()
+    }
+}
+
+impl Neighbourhood {
+    pub const ALL: [Neighbourhood; 4] = [
+        Neighbourhood::Moore,
+        Neighbourhood::VonNeumann,
+        Neighbourhood::Hexagonal,
+        Neighbourhood::Custom(Mask::MOORE),
+    ];
+
+    // Décalage (dx, dy) de chaque voisin d'une cellule, qui dépend de la
+    // parité de sa ligne en hexagonal. Pour Moore, l'ordre est celui de
+    // MOORE_NEIGHBOURS, dont dépendent les règles isotropes.
+    pub fn offsets(&self, odd_row: bool) -> Vec<(isize, isize)> {
+        match self {
+            Neighbourhood::Moore => MOORE_NEIGHBOURS.to_vec(),
+            Neighbourhood::VonNeumann => vec![(0, -1), (1, 0), (0, 1), (-1, 0)],
+            Neighbourhood::Hexagonal if odd_row => {
+                vec![(0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 0)]
+            }
+            Neighbourhood::Hexagonal => vec![(-1, -1), (0, -1), (1, 0), (0, 1), (-1, 1), (-1, 0)],
+            Neighbourhood::Custom(mask) => mask.offsets(),
+        }
+    }
+
+    // Les règles isotropes ne sont définies que pour le voisinage de Moore ;
+    // les règles Larger than Life, les automates à une dimension et Lenia
+    // portent leur propre voisinage et laissent le réglage sur Moore.
+    pub fn supports(&self, rule: &Rule) -> bool {
+        *self == Neighbourhood::Moore || rule.counts_neighbours()
+    }
+
+    // Etat suivant d'une cellule dont le voisin i (dans l'ordre de `offsets`)
+    // est vivant si le bit i de `living` est allumé.
+    pub fn next_state(&self, rule: &Rule, state: u8, living: u32) -> u8 {
+        match self {
+            Neighbourhood::Moore => rule.next_state_for(state, living as u8),
+            _ => rule.next_state(state, living.count_ones() as usize),
+        }
+    }
+}
+
+impl fmt::Display for Neighbourhood {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Neighbourhood::Moore => "Moore",
+            Neighbourhood::VonNeumann => "Von Neumann",
+            Neighbourhood::Hexagonal => "Hexagonal",
+            Neighbourhood::Custom(_) => "Personnalisé",
+        };
+        write!(f, "{}", name)
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashSet;
+
+use crate::census::{classify, ObjectKind};
+use crate::neighbourhood::Neighbourhood;
+use crate::rule::Rule;
+
+// Recherche d'oscillateurs et de vaisseaux : les motifs de la génération 0
+// qui tiennent dans un rectangle width x height et qui, au bout de `period`
+// générations exactement, se retrouvent déplacés de `displacement`. Chaque
+// génération intermédiaire doit tenir dans le rectangle élargi du
+// déplacement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeriodicSearch {
+    pub width: usize,
+    pub height: usize,
+    pub period: usize,
+    pub displacement: (i64, i64),
+}
+
+// Un motif trouvé, ses cellules vivantes repérées dans le rectangle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Found {
+    pub cells: Vec<(i64, i64)>,
+    pub kind: ObjectKind,
+    pub code: String,
+}
+
+// Les motifs trouvés, et si toutes les possibilités ont été essayées.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchOutcome {
+    pub found: Vec<Found>,
+    pub complete: bool,
+}
+
+impl PeriodicSearch {
+    pub const MAX_SIZE: usize = 16;
+    pub const MAX_PERIOD: usize = 8;
+    // La recherche s'arrête après MAX_RESULTS motifs différents ou
+    // MAX_NODES lignes essayées.
+    pub const MAX_RESULTS: usize = 20;
+    const MAX_NODES: u64 = 2_000_000;
+
+    pub fn validate(&self, rule: &Rule, neighbourhood: Neighbourhood) -> Result<(), String> {
+        if !(1..=Self::MAX_SIZE).contains(&self.width)
+            || !(1..=Self::MAX_SIZE).contains(&self.height)
+        {
+            return Err(format!(
+                "Le rectangle doit mesurer de 1 à {} cellules de côté",
+                Self::MAX_SIZE
+            ));
+        }
+        if !(1..=Self::MAX_PERIOD).contains(&self.period) {
+            return Err(format!(
+                "La période doit être comprise entre 1 et {}",
+                Self::MAX_PERIOD
+            ));
+        }
+        if rule.states() != 2
+            || rule.is_larger_than_life()
+            || rule.is_elementary()
+            || rule.is_continuous()
+        {
+            return Err("La recherche demande une règle à deux états".to_string());
+        }
+        if neighbourhood.next_state(rule, 0, 0) != 0 {
+            return Err("Les règles avec B0 ne sont pas gérées".to_string());
+        }
+        let (dx, dy) = self.displacement;
+        let reach = (self.period * radius(neighbourhood)) as i64;
+        if dx.abs() > reach || dy.abs() > reach {
+            return Err(format!(
+                "Le déplacement ne peut pas dépasser {} cellules en {} générations",
+                reach, self.period
+            ));
+        }
+        if neighbourhood == Neighbourhood::Hexagonal && dy % 2 != 0 {
+            return Err("En hexagonal, le déplacement vertical doit être pair".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn run(&self, rule: &Rule, neighbourhood: Neighbourhood) -> Result<SearchOutcome, String> {
+        self.validate(rule, neighbourhood)?;
+        let mut searcher = Searcher::new(self, rule, neighbourhood);
+        let complete = searcher.descend(0);
+        Ok(SearchOutcome {
+            found: searcher.found,
+            complete,
+        })
+    }
+}
+
+// Plus grand écart entre une cellule et ses voisins.
+fn radius(neighbourhood: Neighbourhood) -> usize {
+    [false, true]
+        .iter()
+        .flat_map(|&odd_row| neighbourhood.offsets(odd_row))
+        .map(|(dx, dy)| dx.unsigned_abs().max(dy.unsigned_abs()))
+        .max()
+        .unwrap_or(1)
+}
+
+// Les lignes de la génération 0 sont choisies de haut en bas. Une fois la
+// ligne k choisie, la ligne k - t * r de la génération t (r le rayon du
+// voisinage) ne dépend plus des lignes suivantes : elle est calculée et
+// vérifiée aussitôt, ce qui coupe les branches sans issue.
+// Chaque génération est une grille couvrant la zone permise, élargie de
+// deux fois le rayon ; (x, y) y sont les coordonnées dans le rectangle.
+struct Searcher<'a> {
+    search: &'a PeriodicSearch,
+    rule: &'a Rule,
+    neighbourhood: Neighbourhood,
+    offsets: [Vec<(isize, isize)>; 2],
+    radius: i64,
+    // Zone permise (bornes incluses) et coin supérieur gauche des grilles.
+    area: (i64, i64, i64, i64),
+    origin: (i64, i64),
+    stride: usize,
+    generations: Vec<Vec<bool>>,
+    nodes: u64,
+    found: Vec<Found>,
+    codes: HashSet<String>,
+}
+
+impl<'a> Searcher<'a> {
+    fn new(search: &'a PeriodicSearch, rule: &'a Rule, neighbourhood: Neighbourhood) -> Self {
+        let radius = radius(neighbourhood) as i64;
+        let (dx, dy) = search.displacement;
+        let area = (
+            dx.min(0),
+            dy.min(0),
+            search.width as i64 - 1 + dx.max(0),
+            search.height as i64 - 1 + dy.max(0),
+        );
+        let origin = (area.0 - 2 * radius, area.1 - 2 * radius);
+        let stride = (area.2 - area.0 + 1 + 4 * radius) as usize;
+        let rows = (area.3 - area.1 + 1 + 4 * radius) as usize;
+        Searcher {
+            search,
+            rule,
+            neighbourhood,
+            offsets: [neighbourhood.offsets(false), neighbourhood.offsets(true)],
+            radius,
+            area,
+            origin,
+            stride,
+            generations: vec![vec![false; stride * rows]; search.period + 1],
+            nodes: 0,
+            found: Vec::new(),
+            codes: HashSet::new(),
+        }
+    }
+
+    fn index(&self, x: i64, y: i64) -> Option<usize> {
+        let (x, y) = (x - self.origin.0, y - self.origin.1);
+        if x < 0 || y < 0 || x as usize >= self.stride {
+            return None;
+        }
+        let index = y as usize * self.stride + x as usize;
+        (index < self.generations[0].len()).then_some(index)
+    }
+
+    fn get(&self, generation: usize, x: i64, y: i64) -> bool {
+        self.index(x, y)
+            .is_some_and(|index| self.generations[generation][index])
+    }
+
+    fn set(&mut self, generation: usize, x: i64, y: i64, alive: bool) {
+        if let Some(index) = self.index(x, y) {
+            self.generations[generation][index] = alive;
+        }
+    }
+
+    // Essaie toutes les lignes k possibles, puis les suivantes ; faux si la
+    // recherche s'est arrêtée avant d'avoir tout essayé.
+    fn descend(&mut self, k: usize) -> bool {
+        if k == self.search.height {
+            self.finish();
+            return self.found.len() < PeriodicSearch::MAX_RESULTS;
+        }
+        for row in 0..1u32 << self.search.width {
+            self.nodes += 1;
+            if self.nodes > PeriodicSearch::MAX_NODES {
+                return false;
+            }
+            for x in 0..self.search.width {
+                self.set(0, x as i64, k as i64, row >> x & 1 == 1);
+            }
+            if self.determine(k as i64) && !self.descend(k + 1) {
+                return false;
+            }
+        }
+        for x in 0..self.search.width {
+            self.set(0, x as i64, k as i64, false);
+        }
+        true
+    }
+
+    // Calcule et vérifie les lignes de chaque génération que fixe le choix
+    // de la ligne k de la génération 0.
+    fn determine(&mut self, k: i64) -> bool {
+        let (x_min, y_min, x_max, y_max) = self.area;
+        let (dx, dy) = self.search.displacement;
+        let period = self.search.period;
+        for t in 1..=period {
+            let y = k - t as i64 * self.radius;
+            // Plus loin, rien ne peut naître.
+            if y < y_min - self.radius || y > y_max + self.radius {
+                continue;
+            }
+            for x in x_min - self.radius..=x_max + self.radius {
+                let alive = self.next(t - 1, x, y);
+                self.set(t, x, y, alive);
+                let outside = x < x_min || x > x_max || y < y_min || y > y_max;
+                if alive && outside {
+                    return false;
+                }
+                if t == period && alive != self.get(0, x - dx, y - dy) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn next(&self, generation: usize, x: i64, y: i64) -> bool {
+        let offsets = &self.offsets[y.rem_euclid(2) as usize];
+        let living = offsets
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(dx, dy))| self.get(generation, x + dx as i64, y + dy as i64))
+            .fold(0, |living, (i, _)| living | 1 << i);
+        let state = self.get(generation, x, y) as u8;
+        self.rule
+            .is_alive(self.neighbourhood.next_state(self.rule, state, living))
+    }
+
+    // La génération 0 est complète : il reste à calculer les dernières
+    // lignes, puis à écarter le motif vide, ceux de période plus courte et
+    // ceux déjà trouvés dans une autre position, phase ou orientation.
+    fn finish(&mut self) {
+        let last = self.area.3 + self.radius * (1 + self.search.period as i64);
+        for k in self.search.height as i64..=last {
+            if !self.determine(k) {
+                return;
+            }
+        }
+        let mut cells = Vec::new();
+        for y in 0..self.search.height as i64 {
+            for x in 0..self.search.width as i64 {
+                if self.get(0, x, y) {
+                    cells.push((x, y));
+                }
+            }
+        }
+        let (kind, code) = classify(&cells, self.rule, self.neighbourhood);
+        let period = match kind {
+            ObjectKind::StillLife => 1,
+            ObjectKind::Oscillator { period } | ObjectKind::Spaceship { period, .. } => period,
+            ObjectKind::Unknown => return,
+        };
+        if period as usize == self.search.period && self.codes.insert(code.clone()) {
+            self.found.push(Found { cells, kind, code });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(search: PeriodicSearch) -> Vec<String> {
+        let outcome = search.run(&Rule::CONWAY, Neighbourhood::Moore).unwrap();
+        assert!(outcome.complete, "{:?}", search);
+        outcome.found.into_iter().map(|found| found.code).collect()
+    }
+
+    #[test]
+    fn finds_the_blinker() {
+        let search = PeriodicSearch {
+            width: 3,
+            height: 3,
+            period: 2,
+            displacement: (0, 0),
+        };
+        assert_eq!(codes(search), ["xp2_7"]);
+    }
+
+    #[test]
+    fn finds_the_glider() {
+        let search = PeriodicSearch {
+            width: 3,
+            height: 3,
+            period: 4,
+            displacement: (1, 1),
+        };
+        assert_eq!(codes(search), ["xq4_153"]);
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashSet;
+
+use crate::neighbourhood::Neighbourhood;
+use crate::rule::Rule;
+
+// Résultat de la recherche d'un parent d'un motif pris seul sur le plan
+// infini.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Predecessor {
+    // Cellules vivantes d'une configuration dont la génération suivante est
+    // exactement le motif.
+    Parent(Vec<(i64, i64)>),
+    // Le motif entouré de cellules mortes n'a de parent dans aucune
+    // configuration : c'est un jardin d'Eden.
+    GardenOfEden,
+    // Pas de parent proche du motif, mais sans preuve qu'il n'y en a pas
+    // ailleurs, ou recherche arrêtée avant la fin.
+    Unknown { interrupted: bool },
+}
+
+// Un motif dont on cherche un parent, avec la règle et le voisinage qui le
+// font évoluer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParentSearch {
+    pub pattern: Vec<(i64, i64)>,
+    pub rule: Rule,
+    pub neighbourhood: Neighbourhood,
+}
+
+impl ParentSearch {
+    pub fn run(&self) -> Result<Predecessor, String> {
+        find_parent(&self.pattern, &self.rule, self.neighbourhood)
+    }
+}
+
+// Au-delà, la recherche s'arrête sans conclure.
+const MAX_NODES: u64 = 2_000_000;
+// Un voisinage n'est vérifié avant d'être complet que s'il lui manque au
+// plus MAX_UNKNOWN cellules.
+const MAX_UNKNOWN: u32 = 3;
+
+// Cherche d'abord un parent dont les cellules sont à au plus r (le rayon
+// du voisinage) du rectangle du motif : sa génération suivante ne peut pas
+// dépasser 2r, où tout doit être mort hors du motif. S'il n'y en a pas, le
+// motif bordé de r cellules mortes est recherché seul, le parent pouvant
+// s'étendre jusqu'à 2r : sans solution, aucune configuration ne le donne,
+// quoi qu'il y ait autour.
+fn find_parent(
+    pattern: &[(i64, i64)],
+    rule: &Rule,
+    neighbourhood: Neighbourhood,
+) -> Result<Predecessor, String> {
+    if rule.states() != 2
+        || rule.is_larger_than_life()
+        || rule.is_elementary()
+        || rule.is_continuous()
+    {
+        return Err("La recherche d'un parent demande une règle à deux états".to_string());
+    }
+    if neighbourhood.next_state(rule, 0, 0) != 0 {
+        return Err("Les règles avec B0 ne sont pas gérées".to_string());
+    }
+    let Some(bounds) = bounding_box(pattern) else {
+        // Le vide est son propre parent.
+        return Ok(Predecessor::Parent(Vec::new()));
+    };
+    let radius = radius(neighbourhood);
+    let mut exact = Solver::new(
+        pattern,
+        rule,
+        neighbourhood,
+        grow(bounds, radius),
+        grow(bounds, 2 * radius),
+    );
+    match exact.solve(0) {
+        Some(true) => return Ok(Predecessor::Parent(exact.parent())),
+        None => return Ok(Predecessor::Unknown { interrupted: true }),
+        Some(false) => (),
+    }
+    let mut orphan = Solver::new(
+        pattern,
+        rule,
+        neighbourhood,
+        grow(bounds, 2 * radius),
+        grow(bounds, radius),
+    );
+    Ok(match orphan.solve(0) {
+        Some(false) => Predecessor::GardenOfEden,
+        Some(true) => Predecessor::Unknown { interrupted: false },
+        None => Predecessor::Unknown { interrupted: true },
+    })
+}
+
+// Rectangle (x_min, y_min, x_max, y_max), bornes incluses.
+type Bounds = (i64, i64, i64, i64);
+
+fn bounding_box(cells: &[(i64, i64)]) -> Option<Bounds> {
+    cells.iter().fold(None, |bounds, &(x, y)| match bounds {
+        None => Some((x, y, x, y)),
+        Some((x_min, y_min, x_max, y_max)) => {
+            Some((x_min.min(x), y_min.min(y), x_max.max(x), y_max.max(y)))
+        }
+    })
+}
+
+fn grow((x_min, y_min, x_max, y_max): Bounds, margin: i64) -> Bounds {
+    (
+        x_min - margin,
+        y_min - margin,
+        x_max + margin,
+        y_max + margin,
+    )
+}
+
+fn radius(neighbourhood: Neighbourhood) -> i64 {
+    [false, true]
+        .iter()
+        .flat_map(|&odd_row| neighbourhood.offsets(odd_row))
+        .map(|(dx, dy)| dx.abs().max(dy.abs()) as i64)
+        .max()
+        .unwrap_or(1)
+}
+
+// Les cellules du parent dans `free` sont choisies une à une, ligne par
+// ligne, mortes d'abord ; hors de `free`, elles sont mortes. Après chaque
+// choix, chaque cellule de `constrained` dont il fait partie du voisinage
+// doit encore pouvoir prendre son état dans le motif.
+struct Solver<'a> {
+    target: HashSet<(i64, i64)>,
+    rule: &'a Rule,
+    neighbourhood: Neighbourhood,
+    offsets: [Vec<(isize, isize)>; 2],
+    radius: i64,
+    free: Bounds,
+    constrained: Bounds,
+    // None tant que la cellule n'est pas choisie.
+    parent: Vec<Option<bool>>,
+    nodes: u64,
+}
+
+impl<'a> Solver<'a> {
+    fn new(
+        pattern: &[(i64, i64)],
+        rule: &'a Rule,
+        neighbourhood: Neighbourhood,
+        free: Bounds,
+        constrained: Bounds,
+    ) -> Self {
+        let cells = ((free.2 - free.0 + 1) * (free.3 - free.1 + 1)) as usize;
+        Solver {
+            target: pattern.iter().copied().collect(),
+            rule,
+            neighbourhood,
+            offsets: [neighbourhood.offsets(false), neighbourhood.offsets(true)],
+            radius: radius(neighbourhood),
+            free,
+            constrained,
+            parent: vec![None; cells],
+            nodes: 0,
+        }
+    }
+
+    fn width(&self) -> i64 {
+        self.free.2 - self.free.0 + 1
+    }
+
+    fn position(&self, i: usize) -> (i64, i64) {
+        let width = self.width();
+        (
+            self.free.0 + i as i64 % width,
+            self.free.1 + i as i64 / width,
+        )
+    }
+
+    fn get(&self, x: i64, y: i64) -> Option<bool> {
+        let (x_min, y_min, x_max, y_max) = self.free;
+        if x < x_min || x > x_max || y < y_min || y > y_max {
+            return Some(false);
+        }
+        self.parent[((y - y_min) * self.width() + x - x_min) as usize]
+    }
+
+    // Some(true) si un parent est trouvé (il reste dans `parent`),
+    // Some(false) s'il n'y en a pas, None si la recherche a été arrêtée.
+    fn solve(&mut self, i: usize) -> Option<bool> {
+        if i == self.parent.len() {
+            return Some(true);
+        }
+        let (x, y) = self.position(i);
+        for alive in [false, true] {
+            self.nodes += 1;
+            if self.nodes > MAX_NODES {
+                return None;
+            }
+            self.parent[i] = Some(alive);
+            if self.consistent(x, y) && self.solve(i + 1)? {
+                return Some(true);
+            }
+        }
+        self.parent[i] = None;
+        Some(false)
+    }
+
+    // Les cellules imposées autour de (x, y) peuvent-elles encore prendre
+    // leur état ?
+    fn consistent(&self, x: i64, y: i64) -> bool {
+        let (x_min, y_min, x_max, y_max) = self.constrained;
+        for cy in (y - self.radius).max(y_min)..=(y + self.radius).min(y_max) {
+            for cx in (x - self.radius).max(x_min)..=(x + self.radius).min(x_max) {
+                if !self.possible(cx, cy) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    // Essaie toutes les façons de compléter le voisinage de (x, y) quand il
+    // n'y manque que peu de cellules.
+    fn possible(&self, x: i64, y: i64) -> bool {
+        let offsets = &self.offsets[y.rem_euclid(2) as usize];
+        let mut living = 0;
+        let mut unknown = Vec::new();
+        for (i, &(dx, dy)) in offsets.iter().enumerate() {
+            match self.get(x + dx as i64, y + dy as i64) {
+                Some(true) => living |= 1 << i,
+                Some(false) => (),
+                None => unknown.push(1 << i),
+            }
+        }
+        let state = self.get(x, y);
+        let states: &[u8] = match state {
+            Some(alive) => &[alive as u8][..],
+            None => &[0, 1],
+        };
+        let missing = unknown.len() as u32 + state.is_none() as u32;
+        if missing > MAX_UNKNOWN {
+            return true;
+        }
+        let wanted = self.target.contains(&(x, y));
+        states.iter().any(|&state| {
+            (0..1u32 << unknown.len()).any(|choice| {
+                let living = unknown
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| choice >> j & 1 == 1)
+                    .fold(living, |living, (_, bit)| living | bit);
+                let next = self.neighbourhood.next_state(self.rule, state, living);
+                self.rule.is_alive(next) == wanted
+            })
+        })
+    }
+
+    fn parent(&self) -> Vec<(i64, i64)> {
+        (0..self.parent.len())
+            .filter(|&i| self.parent[i] == Some(true))
+            .map(|i| self.position(i))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Cell;
+    use crate::sparse::SparseGrid;
+
+    fn search(pattern: &[(i64, i64)], rule: &str) -> ParentSearch {
+        ParentSearch {
+            pattern: pattern.to_vec(),
+            rule: rule.parse().unwrap(),
+            neighbourhood: Neighbourhood::Moore,
+        }
+    }
+
+    fn living(plane: &SparseGrid) -> HashSet<(i64, i64)> {
+        plane
+            .cells()
+            .filter(|(_, cell)| cell.is_alive())
+            .map(|(cell, _)| cell)
+            .collect()
+    }
+
+    #[test]
+    fn parents_step_to_the_pattern() {
+        let blinker = [(0, 1), (1, 1), (2, 1)];
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        for pattern in [&blinker[..], &glider[..]] {
+            let search = search(pattern, "B3/S23");
+            let Ok(Predecessor::Parent(parent)) = search.run() else {
+                panic!("pas de parent pour {:?}", pattern);
+            };
+            let mut plane = SparseGrid::new();
+            for &(x, y) in &parent {
+                plane.set(x, y, Cell { state: 1 });
+            }
+            let next = plane.step(&search.rule, search.neighbourhood);
+            assert_eq!(
+                living(&next),
+                pattern.iter().copied().collect(),
+                "{:?}",
+                parent
+            );
+        }
+    }
+
+    #[test]
+    fn finds_a_garden_of_eden() {
+        // Sans mort, une cellule vivante le reste : le parent d'un L de
+        // trois cellules serait une partie du L, or le L entier fait
+        // naître une quatrième cellule et deux cellules n'en font naître
+        // aucune.
+        let search = search(&[(0, 0), (0, 1), (1, 1)], "B3/S012345678");
+        assert_eq!(search.run(), Ok(Predecessor::GardenOfEden));
+    }
+}
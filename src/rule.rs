@@ -0,0 +1,933 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::lenia::LEVELS;
+
+// Règle de type "Life-like" : une cellule morte naît si son nombre de voisins
+// vivants est dans `birth`, une cellule vivante survit s'il est dans
+// `survival`. Les deux ensembles sont des masques de bits sur 0..=8.
+// Avec plus de deux états (règles "Generations"), une cellule vivante qui ne
+// survit pas passe par les états 2..states avant de mourir.
+// Les règles isotropes non totalistiques (notation de Hensel, "B2n3/S23-q")
+// dépendent de la disposition des voisins et non de leur seul nombre : elles
+// sont décrites par `isotropic`, les masques restant alors vides. Il en va de
+// même pour les règles "Larger than Life", décrites par `larger_than_life`,
+// pour les automates à une dimension, décrits par `elementary`, et pour
+// WireWorld.
+// En mode continu (Lenia), l'état d'une cellule est sa valeur arrondie,
+// de 0 à LEVELS ; la valeur elle-même et les paramètres sont gérés par la
+// simulation.
+// Les variantes multicolores (Immigration, QuadLife) ont `colours` états
+// vivants, de 1 à `colours` : une cellule vivante garde sa couleur et une
+// cellule qui naît prend la couleur majoritaire de ses voisines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rule {
+    birth: u16,
+    survival: u16,
+    states: u8,
+    isotropic: Option<[Configurations; 2]>,
+    larger_than_life: Option<LargerThanLife>,
+    elementary: Option<Elementary>,
+    colours: u8,
+    wireworld: bool,
+    continuous: bool,
+}
+
+// Règle "Larger than Life" : les voisins vivants sont comptés jusqu'à une
+// distance `radius`, dans un carré ou, avec `von_neumann`, dans un losange,
+// la cellule elle-même comprise si `middle`. Une cellule morte naît si ce
+// nombre est dans l'intervalle `birth`, une vivante survit s'il est dans
+// `survival`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct LargerThanLife {
+    pub(crate) radius: u8,
+    pub(crate) middle: bool,
+    pub(crate) von_neumann: bool,
+    birth: (u16, u16),
+    survival: (u16, u16),
+}
+
+// Automate à une dimension : l'état suivant d'une cellule dépend des
+// `radius` cellules de chaque côté et d'elle-même. Pour les règles de
+// Wolfram ("W30"), le bit de `code` lu est le nombre binaire formé par ces
+// cellules de gauche à droite ; pour les règles totalistiques ("T20,R2"),
+// c'est leur nombre de cellules vivantes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Elementary {
+    pub(crate) radius: u8,
+    pub(crate) code: u32,
+    pub(crate) totalistic: bool,
+}
+
+impl Elementary {
+    const MAX_RADIUS: u8 = 10;
+}
+
+impl LargerThanLife {
+    const MAX_RADIUS: u8 = 50;
+
+    // Nombre de cellules du voisinage, la cellule elle-même comprise.
+    fn cells(&self) -> u16 {
+        let radius = self.radius as u16;
+        if self.von_neumann {
+            2 * radius * (radius + 1) + 1
+        } else {
+            (2 * radius + 1) * (2 * radius + 1)
+        }
+    }
+}
+
+// Ensemble de configurations des huit voisins : le bit i d'une configuration
+// indique si le voisin i est vivant, dans l'ordre N, NE, E, SE, S, SW, W, NW.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+struct Configurations([u64; 4]);
+
+impl Configurations {
+    fn contains(&self, configuration: u8) -> bool {
+        self.0[configuration as usize / 64] >> (configuration % 64) & 1 == 1
+    }
+
+    fn insert(&mut self, configuration: u8) {
+        self.0[configuration as usize / 64] |= 1 << (configuration % 64);
+    }
+
+    // Masque des nombres de voisins si l'ensemble ne dépend que du nombre de
+    // voisins vivants.
+    fn counts(&self) -> Option<u16> {
+        (0..=8).try_fold(0, |mask, n| {
+            let mut members = configurations_with(n).map(|c| self.contains(c));
+            let first = members.next().unwrap();
+            match members.all(|member| member == first) {
+                true if first => Some(mask | 1 << n),
+                true => Some(mask),
+                false => None,
+            }
+        })
+    }
+}
+
+// Décalage (dx, dy) de chaque voisin, y croissant vers le bas, dans l'ordre
+// des bits d'une configuration.
+pub(crate) const MOORE_NEIGHBOURS: [(isize, isize); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+// Nom et nombre de couleurs des variantes multicolores.
+const COLOUR_VARIANTS: [(&str, u8); 2] = [("Immigration", 2), ("QuadLife", 4)];
+
+// Un représentant de chaque classe de Hensel pour 1 à 4 voisins. Pour 5 à 7
+// voisins, la classe d'une lettre est le complément de celle de même lettre
+// pour 8 - n voisins ; 0 et 8 voisins n'ont qu'une classe, sans lettre.
+// Les bits suivent MOORE_NEIGHBOURS : du bit 0 au bit 7, les voisins N, NE,
+// E, SE, S, SO, O et NO.
+const HENSEL: [&[(char, u8)]; 5] = [
+    &[],
+    &[('c', 0b10), ('e', 0b1)],
+    &[
+        ('c', 0b1010),
+        ('e', 0b101),
+        ('k', 0b1001),
+        ('a', 0b11),
+        ('i', 0b10001),
+        ('n', 0b100010),
+    ],
+    &[
+        ('c', 0b101010),
+        ('e', 0b10101),
+        ('k', 0b100101),
+        ('a', 0b111),
+        ('i', 0b10000011),
+        ('n', 0b1011),
+        ('y', 0b101001),
+        ('q', 0b100011),
+        ('j', 0b1000011),
+        ('r', 0b10011),
+    ],
+    &[
+        ('c', 0b10101010),
+        ('e', 0b1010101),
+        ('k', 0b1001011),
+        ('a', 0b1111),
+        ('i', 0b11011),
+        ('n', 0b10001011),
+        ('y', 0b101011),
+        ('q', 0b100111),
+        ('j', 0b1010011),
+        ('r', 0b10111),
+        ('t', 0b10010011),
+        ('w', 0b1100011),
+        ('z', 0b110011),
+    ],
+];
+
+fn configurations_with(living_neighbours: u32) -> impl Iterator<Item = u8> {
+    (0..=255u8).filter(move |c| c.count_ones() == living_neighbours)
+}
+
+// Les huit images d'une configuration par rotation et symétrie.
+fn symmetries(configuration: u8) -> [u8; 8] {
+    let mirror = (0..8).fold(0u8, |mirror, i| {
+        mirror | (configuration >> i & 1) << ((8 - i) % 8)
+    });
+    let mut images = [0; 8];
+    for k in 0..4 {
+        images[2 * k] = configuration.rotate_left(2 * k as u32);
+        images[2 * k + 1] = mirror.rotate_left(2 * k as u32);
+    }
+    images
+}
+
+// Lettres de Hensel possibles pour n voisins vivants, avec leurs classes.
+fn hensel_letters(living_neighbours: u32) -> impl Iterator<Item = (char, [u8; 8])> {
+    let (representatives, complement) = match living_neighbours {
+        0 | 8 => (HENSEL[0], false),
+        n if n <= 4 => (HENSEL[n as usize], false),
+        n => (HENSEL[8 - n as usize], true),
+    };
+    representatives
+        .iter()
+        .map(move |&(letter, representative)| {
+            let representative = if complement {
+                !representative
+            } else {
+                representative
+            };
+            (letter, symmetries(representative))
+        })
+}
+
+fn hensel_letter(configuration: u8) -> Option<char> {
+    hensel_letters(configuration.count_ones())
+        .find(|(_, class)| class.contains(&configuration))
+        .map(|(letter, _)| letter)
+}
+
+impl Rule {
+    pub const CONWAY: Rule = Rule {
+        birth: 1 << 3,
+        survival: 1 << 2 | 1 << 3,
+        states: 2,
+        isotropic: None,
+        larger_than_life: None,
+        elementary: None,
+        colours: 0,
+        continuous: false,
+        wireworld: false,
+    };
+
+    // Circuits WireWorld : 0 vide, 1 tête d'électron, 2 queue d'électron et
+    // 3 conducteur. La tête devient queue, la queue redevient conducteur, et
+    // un conducteur devient tête s'il touche une ou deux têtes.
+    pub const WIREWORLD: Rule = Rule {
+        birth: 0,
+        survival: 0,
+        states: 4,
+        isotropic: None,
+        larger_than_life: None,
+        elementary: None,
+        colours: 0,
+        continuous: false,
+        wireworld: true,
+    };
+
+    // Automate continu : chaque cellule porte une valeur entre 0 et 1.
+    pub const LENIA: Rule = Rule {
+        birth: 0,
+        survival: 0,
+        states: LEVELS + 1,
+        isotropic: None,
+        larger_than_life: None,
+        elementary: None,
+        colours: 0,
+        wireworld: false,
+        continuous: true,
+    };
+
+    // Quelques règles connues, avec leur nom usuel.
+    pub const PRESETS: [(&'static str, &'static str); 23] = [
+        ("Conway", "B3/S23"),
+        ("HighLife", "B36/S23"),
+        ("Seeds", "B2/S"),
+        ("Day & Night", "B3678/S34678"),
+        ("Life without Death", "B3/S012345678"),
+        ("2x2", "B36/S125"),
+        ("Morley", "B368/S245"),
+        ("Replicator", "B1357/S1357"),
+        ("Maze", "B3/S12345"),
+        ("Diamoeba", "B35678/S5678"),
+        ("Brian's Brain", "B2/S/C3"),
+        ("Star Wars", "B2/S345/C4"),
+        ("Frogs", "B34/S12/C3"),
+        ("tlife", "B3/S2-i34q"),
+        ("Just Friends", "B2-a/S12"),
+        ("Bosco", "R5,C0,M1,S34..58,B34..45,NM"),
+        ("Majority", "R4,C0,M1,S41..81,B41..81,NM"),
+        ("WireWorld", "WireWorld"),
+        ("Immigration", "Immigration"),
+        ("QuadLife", "QuadLife"),
+        ("Lenia", "Lenia"),
+        ("Règle 30", "W30"),
+        ("Règle 110", "W110"),
+    ];
+
+    pub fn new(birth: &[usize], survival: &[usize]) -> Rule {
+        let mask = |counts: &[usize]| counts.iter().fold(0, |mask, &n| mask | 1 << n);
+        Rule {
+            birth: mask(birth),
+            survival: mask(survival),
+            states: 2,
+            isotropic: None,
+            larger_than_life: None,
+            elementary: None,
+            colours: 0,
+            continuous: false,
+            wireworld: false,
+        }
+    }
+
+    // Règle décrite par ses configurations de naissance et de survie, ramenée
+    // à des nombres de voisins quand c'est possible.
+    fn from_configurations(birth: Configurations, survival: Configurations, states: u8) -> Rule {
+        match (birth.counts(), survival.counts()) {
+            (Some(birth), Some(survival)) => Rule {
+                birth,
+                survival,
+                states,
+                isotropic: None,
+                larger_than_life: None,
+                elementary: None,
+                colours: 0,
+                continuous: false,
+                wireworld: false,
+            },
+            _ => Rule {
+                birth: 0,
+                survival: 0,
+                states,
+                isotropic: Some([birth, survival]),
+                larger_than_life: None,
+                elementary: None,
+                colours: 0,
+                continuous: false,
+                wireworld: false,
+            },
+        }
+    }
+
+    pub fn with_states(self, states: u8) -> Rule {
+        Rule {
+            states: states.max(2),
+            ..self
+        }
+    }
+
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    // Vrai si la règle ne dépend que du nombre de voisins vivants ; sinon
+    // born et survives ne sont pas utilisables.
+    pub fn is_totalistic(&self) -> bool {
+        self.isotropic.is_none()
+    }
+
+    // Les règles Larger than Life ne passent ni par les configurations des
+    // huit voisins ni par les voisinages : leur voisinage est dans la règle.
+    pub fn is_larger_than_life(&self) -> bool {
+        self.larger_than_life.is_some()
+    }
+
+    pub(crate) fn larger_than_life(&self) -> Option<LargerThanLife> {
+        self.larger_than_life
+    }
+
+    // Les automates à une dimension ne calculent que la ligne suivante de
+    // leur diagramme espace-temps.
+    pub fn is_elementary(&self) -> bool {
+        self.elementary.is_some()
+    }
+
+    pub(crate) fn elementary(&self) -> Option<Elementary> {
+        self.elementary
+    }
+
+    // Vrai si la règle ne dépend que du nombre de voisins vivants parmi ceux
+    // du réglage de voisinage.
+    pub fn counts_neighbours(&self) -> bool {
+        self.is_totalistic()
+            && !self.is_larger_than_life()
+            && !self.is_elementary()
+            && !self.continuous
+    }
+
+    pub fn is_continuous(&self) -> bool {
+        self.continuous
+    }
+
+    pub fn is_wireworld(&self) -> bool {
+        self.wireworld
+    }
+
+    // Nombre de couleurs d'une variante multicolore, 0 sinon.
+    pub fn colours(&self) -> u8 {
+        self.colours
+    }
+
+    // La même règle, sans les couleurs.
+    pub(crate) fn uncoloured(&self) -> Rule {
+        Rule {
+            states: if self.colours > 0 { 2 } else { self.states },
+            colours: 0,
+            ..*self
+        }
+    }
+
+    // Seul l'état 1 est vivant, sauf pour les variantes multicolores où
+    // toutes les couleurs le sont, et du mode continu où toute cellule de
+    // valeur non nulle l'est.
+    pub fn is_alive(&self, state: u8) -> bool {
+        match self.colours {
+            _ if self.continuous => state != 0,
+            0 => state == 1,
+            colours => (1..=colours).contains(&state),
+        }
+    }
+
+    // Au-delà de huit voisins (voisinages personnalisés), aucune cellule ne
+    // naît ni ne survit, sauf pour les règles Larger than Life.
+    pub fn born(&self, living_neighbours: usize) -> bool {
+        match &self.larger_than_life {
+            Some(rule) => (rule.birth.0..=rule.birth.1).contains(&(living_neighbours as u16)),
+            None => living_neighbours <= 8 && self.birth & (1 << living_neighbours) != 0,
+        }
+    }
+
+    pub fn survives(&self, living_neighbours: usize) -> bool {
+        match &self.larger_than_life {
+            Some(rule) => (rule.survival.0..=rule.survival.1).contains(&(living_neighbours as u16)),
+            None => living_neighbours <= 8 && self.survival & (1 << living_neighbours) != 0,
+        }
+    }
+
+    // Seules les cellules à l'état 1 comptent comme voisines vivantes.
+    // Réservé aux règles totalistiques.
+    pub fn next_state(&self, state: u8, living_neighbours: usize) -> u8 {
+        if self.wireworld {
+            return match state {
+                1 => 2,
+                2 => 3,
+                3 if (1..=2).contains(&living_neighbours) => 1,
+                _ => state,
+            };
+        }
+        self.transition(
+            state,
+            self.born(living_neighbours),
+            self.survives(living_neighbours),
+        )
+    }
+
+    // Etat suivant d'après la configuration des huit voisins (voir
+    // MOORE_NEIGHBOURS), pour toutes les règles sauf Larger than Life.
+    pub fn next_state_for(&self, state: u8, configuration: u8) -> u8 {
+        match &self.isotropic {
+            Some([birth, survival]) => self.transition(
+                state,
+                birth.contains(configuration),
+                survival.contains(configuration),
+            ),
+            None => self.next_state(state, configuration.count_ones() as usize),
+        }
+    }
+
+    fn transition(&self, state: u8, born: bool, survives: bool) -> u8 {
+        match state {
+            0 if born => 1,
+            0 => 0,
+            1 if survives => 1,
+            _ if state + 1 >= self.states => 0,
+            _ => state + 1,
+        }
+    }
+
+    // Lit une liste de nombres de voisins, chacun éventuellement suivi de
+    // lettres de Hensel ("2n3") ou d'un moins et des lettres exclues ("3-q").
+    fn parse_configurations(counts: &str) -> Result<Configurations, String> {
+        let mut configurations = Configurations::default();
+        let mut chars = counts.chars().peekable();
+        while let Some(c) = chars.next() {
+            let n = match c.to_digit(10) {
+                Some(n) if n <= 8 => n,
+                _ => return Err(format!("Nombre de voisins invalide : '{}'", c)),
+            };
+            let excluded = chars.next_if_eq(&'-').is_some();
+            let mut letters = String::new();
+            while let Some(letter) = chars.next_if(char::is_ascii_alphabetic) {
+                letters.push(letter.to_ascii_lowercase());
+            }
+            if excluded && letters.is_empty() {
+                return Err(format!("Lettres manquantes après '{}-'", n));
+            }
+            if let Some(letter) = letters
+                .chars()
+                .find(|&letter| hensel_letters(n).all(|(l, _)| l != letter))
+            {
+                return Err(format!("Lettre '{}' invalide pour {} voisins", letter, n));
+            }
+            for configuration in configurations_with(n) {
+                let listed = hensel_letter(configuration).is_some_and(|l| letters.contains(l));
+                if letters.is_empty() || listed != excluded {
+                    configurations.insert(configuration);
+                }
+            }
+        }
+        Ok(configurations)
+    }
+
+    // Notation "R5,C0,M1,S34..58,B34..45,NM" : rayon, nombre d'états (0 pour
+    // deux), cellule comptée ou non, intervalles de survie et de naissance,
+    // voisinage carré (NM) ou en losange (NN).
+    fn parse_larger_than_life(s: &str) -> Result<Rule, String> {
+        let invalid = || format!("Règle invalide : {}", s);
+        let interval = |value: &str| -> Result<(u16, u16), String> {
+            match value.split_once("..").map(|(a, b)| (a.parse(), b.parse())) {
+                Some((Ok(low), Ok(high))) if low <= high => Ok((low, high)),
+                _ => Err(format!("Intervalle invalide : '{}'", value)),
+            }
+        };
+        let mut radius = None;
+        let mut states = 2;
+        let mut middle = false;
+        let mut von_neumann = false;
+        let (mut birth, mut survival) = (None, None);
+        for part in s.split(',') {
+            let part = part.trim();
+            let Some(key) = part.chars().next() else {
+                return Err(invalid());
+            };
+            let value = &part[key.len_utf8()..];
+            match key.to_ascii_uppercase() {
+                'R' => match value.parse() {
+                    Ok(r) if (1..=LargerThanLife::MAX_RADIUS).contains(&r) => radius = Some(r),
+                    _ => return Err(format!("Rayon invalide : '{}'", value)),
+                },
+                'C' if value == "0" => states = 2,
+                'C' => states = Self::parse_states(value)?,
+                'M' => match value {
+                    "0" => middle = false,
+                    "1" => middle = true,
+                    _ => return Err(invalid()),
+                },
+                'S' => survival = Some(interval(value)?),
+                'B' => birth = Some(interval(value)?),
+                'N' => match value {
+                    "M" | "m" => von_neumann = false,
+                    "N" | "n" => von_neumann = true,
+                    _ => return Err(format!("Voisinage invalide : '{}'", value)),
+                },
+                _ => return Err(invalid()),
+            }
+        }
+        let (Some(radius), Some(birth), Some(survival)) = (radius, birth, survival) else {
+            return Err(invalid());
+        };
+        let rule = LargerThanLife {
+            radius,
+            middle,
+            von_neumann,
+            birth,
+            survival,
+        };
+        if birth.1.max(survival.1) > rule.cells() {
+            return Err(format!(
+                "Le voisinage ne compte que {} cellules",
+                rule.cells()
+            ));
+        }
+        Ok(Rule {
+            birth: 0,
+            survival: 0,
+            states,
+            isotropic: None,
+            larger_than_life: Some(rule),
+            elementary: None,
+            colours: 0,
+            continuous: false,
+            wireworld: false,
+        })
+    }
+
+    // "QuadLife" ou "B36/S23/QuadLife" : la variante multicolore de la règle
+    // qui précède le nom, B3/S23 par défaut. Renvoie None si la règle ne
+    // finit pas par un nom de variante.
+    fn parse_colours(s: &str) -> Option<Result<Rule, String>> {
+        let (name, colours) = COLOUR_VARIANTS.iter().find(|(name, _)| {
+            s.get(s.len().saturating_sub(name.len())..)
+                .is_some_and(|end| end.eq_ignore_ascii_case(name))
+        })?;
+        let base = s[..s.len() - name.len()].trim_end_matches('/').trim();
+        let base = match base {
+            "" => Rule::CONWAY,
+            base => match base.parse::<Rule>() {
+                Ok(base) => base,
+                Err(e) => return Some(Err(e)),
+            },
+        };
+        if base.states != 2 || base.is_larger_than_life() || base.is_elementary() {
+            return Some(Err(format!("{} demande une règle B/S à deux états", name)));
+        }
+        Some(Ok(Rule {
+            states: colours + 1,
+            colours: *colours,
+            ..base
+        }))
+    }
+
+    // Notation "W30" des règles de Wolfram et "T20,R2" des règles
+    // totalistiques, le rayon valant 1 par défaut.
+    fn parse_elementary(s: &str) -> Result<Rule, String> {
+        let invalid = || format!("Règle invalide : {}", s);
+        let (code, radius) = match s[1..].split_once(',') {
+            Some((code, radius)) => match radius.trim().strip_prefix(['R', 'r']) {
+                Some(radius) => (code, radius),
+                None => return Err(invalid()),
+            },
+            None => (&s[1..], "1"),
+        };
+        let totalistic = s.starts_with(['T', 't']);
+        let radius = match radius.parse() {
+            Ok(r) if totalistic && (1..=Elementary::MAX_RADIUS).contains(&r) => r,
+            Ok(1) => 1,
+            _ => return Err(format!("Rayon invalide : '{}'", radius)),
+        };
+        // Un bit par nombre de cellules vivantes, ou par motif de trois
+        // cellules.
+        let bits = if totalistic { 2 * radius as u32 + 2 } else { 8 };
+        let code = match code.trim().parse::<u32>() {
+            Ok(code) if code.checked_shr(bits).unwrap_or(0) == 0 => code,
+            _ => return Err(format!("Code invalide : '{}'", code.trim())),
+        };
+        Ok(Rule {
+            birth: 0,
+            survival: 0,
+            states: 2,
+            isotropic: None,
+            larger_than_life: None,
+            elementary: Some(Elementary {
+                radius,
+                code,
+                totalistic,
+            }),
+            colours: 0,
+            continuous: false,
+            wireworld: false,
+        })
+    }
+
+    fn parse_states(states: &str) -> Result<u8, String> {
+        match states.parse() {
+            Ok(n) if n >= 2 => Ok(n),
+            _ => Err(format!("Nombre d'états invalide : '{}'", states)),
+        }
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::CONWAY
+    }
+}
+
+// Accepte la notation "B36/S23" (dans un ordre quelconque, casse et barre
+// oblique facultatives) ainsi que la notation historique "S/B" comme "23/36".
+// Les règles "Generations" ajoutent le nombre d'états : "B2/S/C3" ou "/2/3".
+// Les règles Larger than Life commencent par leur rayon : "R5,C0,...", les
+// automates à une dimension par W ou T : "W30", "T20,R2", WireWorld et Lenia
+// s'écrivent par leur nom et les variantes multicolores ajoutent le leur :
+// "QuadLife", "B36/S23/Immigration".
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || format!("Règle invalide : {}", s);
+
+        if let Some(rule) = Self::parse_colours(s) {
+            rule
+        } else if s.eq_ignore_ascii_case("wireworld") {
+            Ok(Rule::WIREWORLD)
+        } else if s.eq_ignore_ascii_case("lenia") {
+            Ok(Rule::LENIA)
+        } else if s.starts_with(['R', 'r']) {
+            Self::parse_larger_than_life(s)
+        } else if s.starts_with(['W', 'w', 'T', 't']) {
+            Self::parse_elementary(s)
+        } else if s.contains(['B', 'b', 'S', 's']) {
+            // Les lettres de Hensel sont en minuscules : un "c" n'annonce le
+            // nombre d'états qu'en majuscule ou juste après une barre oblique.
+            let mut sections: Vec<(char, String)> = Vec::new();
+            let mut after_slash = true;
+            for c in s.chars() {
+                match c {
+                    '/' => {
+                        after_slash = true;
+                        continue;
+                    }
+                    'B' | 'b' | 'S' | 's' | 'C' => {
+                        sections.push((c.to_ascii_uppercase(), String::new()))
+                    }
+                    'c' if after_slash => sections.push(('C', String::new())),
+                    _ => match sections.last_mut() {
+                        Some((_, body)) => body.push(c),
+                        None => return Err(invalid()),
+                    },
+                }
+                after_slash = false;
+            }
+            let section = |letter: char| {
+                let mut bodies = sections.iter().filter(|(l, _)| *l == letter);
+                match (bodies.next(), bodies.next()) {
+                    (Some((_, body)), None) => Ok(Some(body.as_str())),
+                    (None, None) => Ok(None),
+                    _ => Err(invalid()),
+                }
+            };
+            let (Some(birth), Some(survival)) = (section('B')?, section('S')?) else {
+                return Err(invalid());
+            };
+            let states = match section('C')? {
+                Some(states) => Self::parse_states(states)?,
+                None => 2,
+            };
+            Ok(Rule::from_configurations(
+                Self::parse_configurations(birth)?,
+                Self::parse_configurations(survival)?,
+                states,
+            ))
+        } else {
+            let parts: Vec<&str> = s.split('/').collect();
+            let states = match parts.len() {
+                2 => 2,
+                3 => Self::parse_states(parts[2])?,
+                _ => return Err(invalid()),
+            };
+            Ok(Rule::from_configurations(
+                Self::parse_configurations(parts[1])?,
+                Self::parse_configurations(parts[0])?,
+                states,
+            ))
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts = |mask: u16| -> String {
+            (0..=8)
+                .filter(|n| mask & (1 << n) != 0)
+                .map(|n| char::from(b'0' + n as u8))
+                .collect()
+        };
+        // Pour chaque nombre de voisins, les lettres présentes, ou un moins et
+        // les lettres absentes si elles sont moins nombreuses.
+        let hensel = |configurations: &Configurations| -> String {
+            let mut out = String::new();
+            for n in 0..=8 {
+                let (present, absent): (Vec<_>, Vec<_>) = hensel_letters(n)
+                    .map(|(letter, class)| (letter, configurations.contains(class[0])))
+                    .partition(|&(_, contained)| contained);
+                let letters = |list: Vec<(char, bool)>| -> String {
+                    list.into_iter().map(|(letter, _)| letter).collect()
+                };
+                let all = configurations_with(n).all(|c| configurations.contains(c));
+                let none = configurations_with(n).all(|c| !configurations.contains(c));
+                if all {
+                    out.push_str(&n.to_string());
+                } else if !none && present.len() <= absent.len() {
+                    out.push_str(&format!("{}{}", n, letters(present)));
+                } else if !none {
+                    out.push_str(&format!("{}-{}", n, letters(absent)));
+                }
+            }
+            out
+        };
+        if self.wireworld {
+            return write!(f, "WireWorld");
+        }
+        if self.continuous {
+            return write!(f, "Lenia");
+        }
+        if let Some((name, _)) = COLOUR_VARIANTS.iter().find(|(_, n)| *n == self.colours) {
+            return match self.uncoloured() {
+                Rule::CONWAY => write!(f, "{}", name),
+                base => write!(f, "{}/{}", base, name),
+            };
+        }
+        if let Some(rule) = &self.elementary {
+            return match rule.totalistic {
+                true => write!(f, "T{},R{}", rule.code, rule.radius),
+                false => write!(f, "W{}", rule.code),
+            };
+        }
+        if let Some(rule) = &self.larger_than_life {
+            return write!(
+                f,
+                "R{},C{},M{},S{}..{},B{}..{},N{}",
+                rule.radius,
+                if self.states > 2 { self.states } else { 0 },
+                rule.middle as u8,
+                rule.survival.0,
+                rule.survival.1,
+                rule.birth.0,
+                rule.birth.1,
+                if rule.von_neumann { 'N' } else { 'M' },
+            );
+        }
+        match &self.isotropic {
+            Some([birth, survival]) => write!(f, "B{}/S{}", hensel(birth), hensel(survival))?,
+            None => write!(f, "B{}/S{}", counts(self.birth), counts(self.survival))?,
+        }
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Rule {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hensel_letters_partition_each_neighbour_count() {
+        let classes = [2, 6, 10, 13, 10, 6, 2];
+        for n in 1..=7 {
+            let letters: Vec<(char, [u8; 8])> = hensel_letters(n).collect();
+            assert_eq!(letters.len(), classes[n as usize - 1], "{} voisins", n);
+            for configuration in configurations_with(n) {
+                let owners = letters
+                    .iter()
+                    .filter(|(_, class)| class.contains(&configuration))
+                    .count();
+                assert_eq!(
+                    owners, 1,
+                    "{} voisins, configuration {:08b}",
+                    n, configuration
+                );
+            }
+            for (letter, class) in &letters {
+                assert!(class.iter().all(|c| c.count_ones() == n), "{}{}", n, letter);
+            }
+        }
+    }
+
+    // Configuration dessinée sur trois lignes séparées par des barres
+    // obliques, du nord au sud, la cellule centrale au milieu.
+    fn drawn(picture: &str) -> u8 {
+        let rows: Vec<&[u8]> = picture.split('/').map(str::as_bytes).collect();
+        MOORE_NEIGHBOURS
+            .iter()
+            .enumerate()
+            .fold(0, |configuration, (i, &(dx, dy))| {
+                let cell = rows[(dy + 1) as usize][(dx + 1) as usize];
+                configuration | ((cell == b'o') as u8) << i
+            })
+    }
+
+    // Une forme de chaque lettre, d'après les dessins de LifeWiki.
+    #[test]
+    fn hensel_letters_name_their_shapes() {
+        let shapes = [
+            ("1c", "o../.../..."),
+            ("1e", ".o./.../..."),
+            ("2c", "o.o/.../..."),
+            ("2e", ".o./o../..."),
+            ("2k", ".o./.../..o"),
+            ("2a", "oo./.../..."),
+            ("2i", ".o./.../.o."),
+            ("2n", "o../.../..o"),
+            ("3c", "o.o/.../..o"),
+            ("3e", ".o./o.o/..."),
+            ("3k", ".o./..o/o.."),
+            ("3a", "oo./o../..."),
+            ("3i", "o../o../o.."),
+            ("3n", "o.o/o../..."),
+            ("3y", "o.o/.../.o."),
+            ("3q", ".oo/.../o.."),
+            ("3j", "..o/..o/.o."),
+            ("3r", ".oo/.../.o."),
+            ("4c", "o.o/.../o.o"),
+            ("4e", ".o./o.o/.o."),
+            ("4k", ".oo/o../..o"),
+            ("4a", "oo./o../o.."),
+            ("4i", "o.o/o.o/..."),
+            ("4n", "ooo/.../..o"),
+            ("4y", ".oo/.../o.o"),
+            ("4q", ".oo/..o/o.."),
+            ("4j", ".oo/o../.o."),
+            ("4r", ".oo/..o/.o."),
+            ("4t", "ooo/.../.o."),
+            ("4w", ".oo/o../o.."),
+            ("4z", ".oo/.../oo."),
+        ];
+        for (name, picture) in shapes {
+            let configuration = drawn(picture);
+            let (n, letter) = (name[..1].parse::<u32>().unwrap(), name[1..].chars().next());
+            assert_eq!(configuration.count_ones(), n, "{}", name);
+            assert_eq!(hensel_letter(configuration), letter, "{}", name);
+            // La même lettre pour 8 - n voisins désigne le complément.
+            if n < 4 {
+                assert_eq!(hensel_letter(!configuration), letter, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn all_hensel_letters_give_the_totalistic_rule() {
+        let letters: String = hensel_letters(3).map(|(letter, _)| letter).collect();
+        let rule: Rule = format!("B3{}/S23", letters).parse().unwrap();
+        for state in 0..2 {
+            for configuration in 0..=255 {
+                assert_eq!(
+                    rule.next_state_for(state, configuration),
+                    Rule::CONWAY.next_state_for(state, configuration)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn larger_than_life_rejects_non_ascii_keys() {
+        assert!("R5,é".parse::<Rule>().is_err());
+        assert!("R5,C0,M1,S34..58,B34..45,NM,é1".parse::<Rule>().is_err());
+        assert!("R5,C0,M1,S34..58,B34..45,NM".parse::<Rule>().is_ok());
+    }
+}
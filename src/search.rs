@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use conway::{Census, Soup, SoupSearch};
+
+// Nombre de graines citées pour chaque objet rare.
+const MAX_SEEDS: usize = 10;
+
+// Fait évoluer `soups` soupes, de graines `first_seed`, `first_seed + 1`, ...,
+// réparties entre `threads` fils. Le recensement de chaque soupe est écrit
+// dans ./exports/soupes{n}.csv ; le total est affiché, suivi des objets
+// rares, trouvés dans au plus 1 % des soupes, avec les graines qui les
+// redonnent.
+pub fn run(
+    search: SoupSearch,
+    soups: usize,
+    first_seed: u64,
+    threads: usize,
+) -> Result<(), String> {
+    let done = AtomicUsize::new(0);
+    let mut results: Vec<Soup> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| {
+                let done = &done;
+                scope.spawn(move || {
+                    let mut results = Vec::new();
+                    for i in (worker..soups).step_by(threads) {
+                        results.push(search.run(first_seed.wrapping_add(i as u64))?);
+                        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                        if done.is_multiple_of(100) || done == soups {
+                            eprint!("\r{}/{} soupes", done, soups);
+                        }
+                    }
+                    Ok::<_, String>(results)
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("Un fil de recherche a paniqué"))
+            .collect::<Result<Vec<Vec<Soup>>, String>>()
+    })?
+    .into_iter()
+    .flatten()
+    .collect();
+    eprintln!();
+    results.sort_by_key(|soup| soup.seed.wrapping_sub(first_seed));
+
+    if let Err(e) = export(&results) {
+        eprintln!("Erreur lors de l'écriture des recensements: {}", e);
+    }
+
+    let mut total = Census::default();
+    // Graines des soupes où apparaît chaque objet.
+    let mut seeds: HashMap<&str, Vec<u64>> = HashMap::new();
+    for soup in &results {
+        total.merge(&soup.census);
+        for entry in soup.census.entries() {
+            seeds.entry(&entry.code).or_default().push(soup.seed);
+        }
+    }
+    println!(
+        "{} soupes {}x{} à {} %, règle {}, graines {} à {}",
+        soups,
+        search.width,
+        search.height,
+        search.density * 100.0,
+        search.rule,
+        first_seed,
+        first_seed.wrapping_add(soups as u64 - 1)
+    );
+    let unsettled = results.iter().filter(|soup| !soup.settled).count();
+    if unsettled > 0 {
+        println!(
+            "{} soupes non stabilisées en {} générations",
+            unsettled, search.max_generations
+        );
+    }
+    println!();
+    println!(
+        "{:<24} {:<16} {:>10} {:>8}",
+        "code", "nom", "nombre", "soupes"
+    );
+    for entry in total.entries() {
+        println!(
+            "{:<24} {:<16} {:>10} {:>8}",
+            entry.code,
+            entry.name.unwrap_or(""),
+            entry.count,
+            seeds[entry.code.as_str()].len()
+        );
+    }
+
+    let rare = (soups / 100).max(1);
+    println!();
+    println!("Objets rares :");
+    for entry in total.entries().iter().rev() {
+        let found = &seeds[entry.code.as_str()];
+        if found.len() > rare {
+            continue;
+        }
+        let listed: Vec<String> = found.iter().take(MAX_SEEDS).map(u64::to_string).collect();
+        println!(
+            "{} {}({}) : graine{} {}",
+            entry.code,
+            entry
+                .name
+                .map_or(String::new(), |name| format!("{} ", name)),
+            entry.kind,
+            if found.len() > 1 { "s" } else { "" },
+            listed.join(", ")
+        );
+    }
+    Ok(())
+}
+
+// Une ligne par soupe : sa graine, sa dernière génération, si elle s'est
+// stabilisée et ses objets.
+fn export(results: &[Soup]) -> Result<(), std::io::Error> {
+    let dossier = Path::new("./exports");
+    fs::create_dir_all(dossier)?;
+    let numéro = fs::read_dir(dossier)?.count();
+    let mut csv = String::from("graine,generation,stabilisee,objets\n");
+    for soup in results {
+        let objects: Vec<String> = soup
+            .census
+            .entries()
+            .iter()
+            .map(|entry| format!("{}:{}", entry.code, entry.count))
+            .collect();
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            soup.seed,
+            soup.generation,
+            soup.settled,
+            objects.join(" ")
+        ));
+    }
+    let file_name = format!("./exports/soupes{}.csv", numéro);
+    fs::write(&file_name, csv)?;
+    eprintln!("Recensements écrits dans {}", file_name);
+    Ok(())
+}
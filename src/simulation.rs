@@ -0,0 +1,1319 @@
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bands::map_bands;
+
+use crate::bitgrid::BitGrid;
+use crate::census::Census;
+use crate::cycles::{block_hash, cells_block_hash, Cycle, CycleDetector, Fingerprint};
+use crate::elementary;
+use crate::grid::{Cell, Grid};
+use crate::hashlife::HashLife;
+use crate::history::{Change, History};
+use crate::larger_than_life;
+use crate::lenia::{Field, Lenia, LEVELS};
+use crate::multicolour;
+use crate::neighbourhood::Neighbourhood;
+use crate::predecessor::ParentSearch;
+use crate::rule::Rule;
+use crate::sparse::SparseGrid;
+use crate::stochastic::Stochastic;
+use crate::tiles::TileMap;
+use crate::topology::Topology;
+
+// Etat d'une partie : la grille courante, la règle appliquée, le voisinage
+// sur lequel elle porte, la topologie des bords, le numéro de génération et le nombre de cellules vivantes, tenu
+// à jour à chaque génération.
+// Sur le plan infini, les cellules sont dans `sparse` et `cells_tab` n'est
+// que la fenêtre visible, dont le coin supérieur gauche est `view_origin`.
+// En mode continu, les valeurs des cellules sont dans `field` et `cells_tab`
+// n'en garde que les niveaux arrondis.
+// Les générations calculées et les cellules modifiées à la main sont
+// gardées dans `history` pour être annulées et rétablies ; l'empreinte de
+// chaque génération est gardée dans `cycles` pour reconnaître une partie qui
+// se répète.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Simulation {
+    cells_tab: Grid,
+    #[serde(default)]
+    rule: Rule,
+    #[serde(default)]
+    neighbourhood: Neighbourhood,
+    #[serde(default)]
+    topology: Topology,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sparse: Option<SparseGrid>,
+    #[serde(default)]
+    view_origin: (i64, i64),
+    #[serde(default)]
+    lenia: Lenia,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    field: Option<Field>,
+    #[serde(default)]
+    stochastic: Stochastic,
+    #[serde(default)]
+    seed: u64,
+    generation: u64,
+    number_of_living_cells: u32,
+    #[serde(skip)]
+    hashlife: Option<HashLife>,
+    #[serde(skip)]
+    bits: Option<BitGrid>,
+    #[serde(skip)]
+    changed_tiles: Option<TileMap>,
+    #[serde(skip)]
+    history: History,
+    // Cellules changées par l'action en train d'être gardée dans
+    // l'historique (voir `record`).
+    #[serde(skip)]
+    journal: Option<Change>,
+    #[serde(skip)]
+    cycles: CycleDetector,
+    // Hachage de la grille bornée, tenu à jour bloc par bloc (voir
+    // cycles::block_hash) ; None s'il est à recalculer.
+    #[serde(skip)]
+    grid_hash: Option<u64>,
+    #[serde(skip)]
+    births_and_deaths: Option<(u32, u32)>,
+    #[serde(skip, default = "Simulation::default_threads")]
+    threads: usize,
+}
+
+// Les états de huit cellules consécutives par mot.
+fn pack(cells: &[Cell]) -> impl Iterator<Item = u64> + '_ {
+    cells.chunks(8).map(|chunk| {
+        chunk
+            .iter()
+            .fold(0, |word, cell| word << 8 | cell.state as u64)
+    })
+}
+
+impl Simulation {
+    // Côté des tuiles suivies par le calcul cellule par cellule.
+    const TILE_SIZE: usize = 16;
+
+    pub fn new(grid: Grid) -> Self {
+        Self::with_rule(grid, Rule::default())
+    }
+
+    pub fn with_rule(grid: Grid, rule: Rule) -> Self {
+        let mut simulation = Simulation {
+            number_of_living_cells: 0,
+            cells_tab: grid,
+            rule,
+            neighbourhood: Neighbourhood::default(),
+            topology: Topology::default(),
+            sparse: None,
+            view_origin: (0, 0),
+            lenia: Lenia::default(),
+            field: None,
+            stochastic: Stochastic::default(),
+            seed: 0,
+            generation: 1,
+            hashlife: None,
+            bits: None,
+            changed_tiles: None,
+            history: History::default(),
+            journal: None,
+            cycles: CycleDetector::default(),
+            grid_hash: None,
+            births_and_deaths: None,
+            threads: Self::default_threads(),
+        };
+        simulation.number_of_living_cells = simulation.count_living_cells();
+        simulation
+    }
+
+    // Un fil par coeur disponible.
+    pub fn default_threads() -> usize {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    // Nombre de fils qui se partagent les lignes de la grille à chaque
+    // génération ; le résultat ne dépend pas de ce nombre.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    // Les cellules vivantes ne sont pas les mêmes d'une règle à l'autre :
+    // les couleurs des variantes multicolores sont des états mourants pour
+    // les autres règles. Quitter le mode continu oublie les valeurs des
+    // cellules, et avec elles l'historique.
+    pub fn set_rule(&mut self, rule: Rule) {
+        if !rule.is_continuous() && self.field.take().is_some() {
+            self.history.clear();
+        }
+        self.rule = rule;
+        self.number_of_living_cells = self.count_living_cells();
+        self.forget_caches();
+        self.forget_cycles();
+    }
+
+    pub fn neighbourhood(&self) -> Neighbourhood {
+        self.neighbourhood
+    }
+
+    // Les règles isotropes demandent le voisinage de Moore (voir
+    // Neighbourhood::supports).
+    pub fn set_neighbourhood(&mut self, neighbourhood: Neighbourhood) {
+        self.neighbourhood = neighbourhood;
+        self.forget_caches();
+        self.forget_cycles();
+    }
+
+    pub fn lenia(&self) -> Lenia {
+        self.lenia
+    }
+
+    pub fn set_lenia(&mut self, lenia: Lenia) {
+        self.lenia = lenia;
+        self.forget_cycles();
+    }
+
+    pub fn stochastic(&self) -> Stochastic {
+        self.stochastic
+    }
+
+    // Sans effet sur les automates à une dimension et continus.
+    pub fn set_stochastic(&mut self, stochastic: Stochastic) {
+        self.stochastic = stochastic;
+        self.forget_cycles();
+    }
+
+    // Graine du hasard de la partie, qui a aussi servi à remplir la grille
+    // initiale.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    // Passer au plan infini y recopie la grille ; le quitter ne garde que la
+    // fenêtre visible. Dans les deux cas, l'historique est oublié.
+    pub fn set_topology(&mut self, topology: Topology) {
+        match (self.sparse.is_some(), topology == Topology::Unbounded) {
+            (false, true) => {
+                self.sparse = Some(SparseGrid::from_grid(&self.cells_tab, 0, 0));
+                self.view_origin = (0, 0);
+                self.history.clear();
+            }
+            (true, false) => {
+                self.sparse = None;
+                self.number_of_living_cells = self.count_living_cells();
+                self.history.clear();
+            }
+            _ => (),
+        }
+        self.topology = topology;
+        self.forget_caches();
+        self.forget_cycles();
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.cells_tab
+    }
+
+    pub fn sparse_grid(&self) -> Option<&SparseGrid> {
+        self.sparse.as_ref()
+    }
+
+    pub fn view_origin(&self) -> (i64, i64) {
+        self.view_origin
+    }
+
+    // Centre la fenêtre visible sur les cellules du plan infini.
+    pub fn center_view(&mut self) {
+        let Some(sparse) = &self.sparse else {
+            return;
+        };
+        if let Some((x_min, y_min, x_max, y_max)) = sparse.bounding_box() {
+            self.view_origin = (
+                (x_min + x_max) / 2 - self.cells_tab.width() as i64 / 2,
+                (y_min + y_max) / 2 - self.cells_tab.height() as i64 / 2,
+            );
+            self.refresh_view();
+        }
+    }
+
+    fn refresh_view(&mut self) {
+        if let Some(sparse) = &self.sparse {
+            let (x0, y0) = self.view_origin;
+            self.cells_tab = sparse.window(x0, y0, self.cells_tab.width(), self.cells_tab.height());
+            self.forget_caches();
+        }
+    }
+
+    // Les tuiles non modifiées ne sont plus recalculées ni hachées ; après
+    // un changement de règle, de topologie ou de fenêtre, une annulation ou
+    // une génération qui remplace toute la grille, tout doit l'être à
+    // nouveau.
+    fn forget_caches(&mut self) {
+        self.bits = None;
+        self.changed_tiles = None;
+        self.grid_hash = None;
+    }
+
+    // Repart de la génération 1 avec les cellules actuelles.
+    pub fn restart(&mut self) {
+        self.generation = 1;
+        self.forget_cycles();
+        self.number_of_living_cells = self.count_living_cells();
+    }
+
+    fn count_living_cells(&self) -> u32 {
+        let rule = self.rule;
+        match &self.sparse {
+            Some(sparse) => sparse
+                .cells()
+                .filter(|(_, cell)| rule.is_alive(cell.state))
+                .count() as u32,
+            None if rule.is_elementary() => {
+                let row = self.cells_tab.row(self.current_row());
+                row.iter().filter(|cell| cell.is_alive()).count() as u32
+            }
+            None => (0..self.cells_tab.height())
+                .flat_map(|y| self.cells_tab.row(y))
+                .filter(|cell| rule.is_alive(cell.state))
+                .count() as u32,
+        }
+    }
+
+    // Nombre de cellules de chaque couleur d'une variante multicolore.
+    pub fn colour_populations(&self) -> Vec<u32> {
+        let mut populations = vec![0; self.rule.colours() as usize];
+        let mut count = |cell: Cell| {
+            if let Some(population) = populations.get_mut((cell.state as usize).wrapping_sub(1)) {
+                *population += 1;
+            }
+        };
+        match &self.sparse {
+            Some(sparse) => sparse.cells().for_each(|(_, cell)| count(cell)),
+            None => (0..self.cells_tab.height())
+                .flat_map(|y| self.cells_tab.row(y))
+                .for_each(|&cell| count(cell)),
+        }
+        populations
+    }
+
+    // Ligne de la génération courante d'un automate à une dimension : la
+    // génération 1 est sur la première ligne.
+    fn current_row(&self) -> usize {
+        (self.generation - 1).min(self.cells_tab.height() as u64 - 1) as usize
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn population(&self) -> u32 {
+        self.number_of_living_cells
+    }
+
+    // En mode continu, une cellule allumée prend la valeur 1.
+    pub fn toggle(&mut self, x: usize, y: usize) {
+        let cell = if self.rule.is_alive(self.cells_tab.get(x, y).state) {
+            Cell::DEAD
+        } else if self.rule.is_continuous() {
+            Cell { state: LEVELS }
+        } else {
+            Cell::ALIVE
+        };
+        self.paint(x, y, cell);
+    }
+
+    // Donne à la cellule (x, y) un état quelconque, par exemple un
+    // conducteur WireWorld.
+    pub fn paint(&mut self, x: usize, y: usize, cell: Cell) {
+        self.record(|simulation| simulation.set_cell(x, y, cell));
+        self.forget_cycles();
+    }
+
+    fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
+        let before = self.cells_tab.get(x, y);
+        self.note_change(x, y, before, cell);
+        let x0 = x - x % 64;
+        if self.grid_hash.is_some() {
+            self.toggle_block_hash(x0, y);
+        }
+        if self.rule.is_alive(before.state) {
+            self.number_of_living_cells -= 1;
+        }
+        if self.rule.is_alive(cell.state) {
+            self.number_of_living_cells += 1;
+        }
+        self.cells_tab.set(x, y, cell);
+        if self.grid_hash.is_some() {
+            self.toggle_block_hash(x0, y);
+        }
+        if self.rule.is_elementary() {
+            self.number_of_living_cells = self.count_living_cells();
+        }
+        // La grille de bits ne connaît que les cellules mortes et vivantes.
+        if cell.state > 1 {
+            self.bits = None;
+        }
+        if let Some(bits) = &mut self.bits {
+            bits.set(x, y, cell.is_alive());
+        }
+        if let Some(changed_tiles) = &mut self.changed_tiles {
+            let tile = changed_tiles.index(x / Self::TILE_SIZE, y / Self::TILE_SIZE);
+            changed_tiles.mark(tile);
+        }
+        if let Some(sparse) = &mut self.sparse {
+            let (x0, y0) = self.view_origin;
+            sparse.set(x0 + x as i64, y0 + y as i64, cell);
+        }
+        if let Some(field) = &mut self.field {
+            field.set(x, y, cell);
+        }
+    }
+
+    // Place `cells` au centre de la fenêtre visible, à la place de toutes
+    // les cellules, sur une ligne paire pour garder le pavage hexagonal.
+    pub fn load_pattern(&mut self, cells: &[(i64, i64)]) {
+        let width = cells.iter().map(|&(x, _)| x + 1).max().unwrap_or(0);
+        let height = cells.iter().map(|&(_, y)| y + 1).max().unwrap_or(0);
+        let (vx, vy) = match self.sparse {
+            Some(_) => self.view_origin,
+            None => (0, 0),
+        };
+        let x0 = vx + (self.cells_tab.width() as i64 - width) / 2;
+        let y0 = vy + (self.cells_tab.height() as i64 - height) / 2;
+        let y0 = y0 - y0.rem_euclid(2);
+        let cells: Vec<(i64, i64)> = cells.iter().map(|&(x, y)| (x0 + x, y0 + y)).collect();
+        self.replace_cells(&cells);
+    }
+
+    // Remplace toutes les cellules par `cells`, repérées dans la grille ou,
+    // sur le plan infini, dans le plan ; annulable comme une modification à
+    // la main. Sur une grille bornée, les cellules qui dépassent sont
+    // perdues.
+    pub fn replace_cells(&mut self, cells: &[(i64, i64)]) {
+        self.record(|simulation| {
+            if let Some(sparse) = &mut simulation.sparse {
+                *sparse = SparseGrid::new();
+                for &(x, y) in cells {
+                    sparse.set(x, y, Cell::ALIVE);
+                }
+                simulation.number_of_living_cells = simulation.count_living_cells();
+                simulation.refresh_view();
+                return;
+            }
+            let (w, h) = (simulation.cells_tab.width(), simulation.cells_tab.height());
+            let mut next = Grid::new(w, h);
+            for &(x, y) in cells {
+                if (0..w as i64).contains(&x) && (0..h as i64).contains(&y) {
+                    next.set(x as usize, y as usize, Cell::ALIVE);
+                }
+            }
+            for y in 0..h {
+                for x in 0..w {
+                    if simulation.cells_tab.get(x, y) != next.get(x, y) {
+                        simulation.set_cell(x, y, next.get(x, y));
+                    }
+                }
+            }
+        });
+        self.forget_cycles();
+    }
+
+    // Recherche d'une configuration dont la génération suivante est la
+    // génération actuelle, prise seule sur le plan infini.
+    pub fn parent_search(&self) -> ParentSearch {
+        ParentSearch {
+            pattern: self.living_positions(),
+            rule: self.rule,
+            neighbourhood: self.neighbourhood,
+        }
+    }
+
+    pub fn step(&mut self) {
+        self.record(Self::advance);
+    }
+
+    fn advance(&mut self) {
+        if self.cycles.is_empty() {
+            self.observe_cycle();
+        }
+        let stochastic = !self.stochastic.is_deterministic()
+            && !self.rule.is_elementary()
+            && !self.rule.is_continuous();
+        let previous = stochastic.then(|| (self.cells_tab.clone(), self.sparse.clone()));
+        match &self.sparse {
+            _ if self.rule.is_continuous() => self.step_continuous(),
+            _ if self.rule.is_elementary() => self.step_elementary(),
+            Some(sparse) => {
+                self.sparse = Some(sparse.step(&self.rule, self.neighbourhood));
+                self.number_of_living_cells = self.count_living_cells();
+                self.refresh_view();
+            }
+            None => self.update_cells(),
+        }
+        if let Some((previous, previous_sparse)) = previous {
+            self.apply_stochastic(&previous, previous_sparse.as_ref());
+        }
+        self.generation += 1;
+        self.observe_cycle();
+    }
+
+    // Ce qu'est devenue la partie, une fois qu'une génération en a répété
+    // une autre. Rien n'est cherché quand le hasard s'en mêle.
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycles.found()
+    }
+
+    fn forget_cycles(&mut self) {
+        self.cycles = CycleDetector::default();
+    }
+
+    fn observe_cycle(&mut self) {
+        if !self.stochastic.is_deterministic() || self.cycles.found().is_some() {
+            return;
+        }
+        let fingerprint = self.fingerprint();
+        self.cycles.observe(self.generation, fingerprint);
+    }
+
+    // Empreinte de la génération courante : les valeurs d'un automate
+    // continu, la ligne courante d'un automate à une dimension, les cellules
+    // du plan infini rapportées au coin de leur rectangle, ou le hachage de
+    // la grille, recalculé seulement s'il n'est plus tenu à jour.
+    pub(crate) fn fingerprint(&mut self) -> Fingerprint {
+        if let (true, Some(field)) = (self.rule.is_continuous(), &self.field) {
+            return Fingerprint::new(field.values().iter().map(|v| v.to_bits() as u64), (0, 0));
+        }
+        if self.rule.is_elementary() {
+            let row = pack(self.cells_tab.row(self.current_row()));
+            return Fingerprint::new(row, (0, 0));
+        }
+        if let Some(sparse) = &self.sparse {
+            let Some((x_min, y_min, _, _)) = sparse.bounding_box() else {
+                return Fingerprint::new(std::iter::empty(), (0, 0));
+            };
+            let mut cells: Vec<u64> = sparse
+                .cells()
+                .map(|((x, y), cell)| {
+                    ((x - x_min) as u64) << 40 | ((y - y_min) as u64) << 8 | cell.state as u64
+                })
+                .collect();
+            cells.sort_unstable();
+            return Fingerprint::new(cells.into_iter(), (x_min, y_min));
+        }
+        let hash = match self.grid_hash {
+            Some(hash) => hash,
+            None => {
+                let grid = &self.cells_tab;
+                let hash = (0..grid.height())
+                    .flat_map(|y| (0..grid.width()).step_by(64).map(move |x0| (x0, y)))
+                    .fold(0, |hash, (x0, y)| hash ^ self.grid_block_hash(grid, x0, y));
+                self.grid_hash = Some(hash);
+                hash
+            }
+        };
+        Fingerprint::from_hash(hash)
+    }
+
+    // Tire au sort les naissances et les survies de la génération qui vient
+    // d'être calculée à partir de `previous`, puis ajoute le bruit. Sur le
+    // plan infini, le bruit ne touche que la fenêtre visible.
+    fn apply_stochastic(&mut self, previous: &Grid, previous_sparse: Option<&SparseGrid>) {
+        let mut rng = Stochastic::rng(self.seed, self.generation);
+        let (stochastic, rule) = (self.stochastic, self.rule);
+        if (stochastic.birth, stochastic.survival) != (1.0, 1.0) {
+            match (&mut self.sparse, previous_sparse) {
+                (Some(sparse), Some(previous)) => {
+                    // Dans un ordre fixe, pour que le tirage se rejoue.
+                    let mut cells: Vec<_> = sparse.cells().collect();
+                    cells.sort_unstable_by_key(|&(position, _)| position);
+                    for ((x, y), next) in cells {
+                        let cell = stochastic.apply(&rule, previous.get(x, y), next, &mut rng);
+                        sparse.set(x, y, cell);
+                    }
+                    self.number_of_living_cells = self.count_living_cells();
+                    self.refresh_view();
+                }
+                _ => {
+                    for y in 0..self.cells_tab.height() {
+                        for x in 0..self.cells_tab.width() {
+                            let next = self.cells_tab.get(x, y);
+                            let cell = stochastic.apply(&rule, previous.get(x, y), next, &mut rng);
+                            if cell != next {
+                                self.set_cell(x, y, cell);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if stochastic.noise > 0.0 {
+            for y in 0..self.cells_tab.height() {
+                for x in 0..self.cells_tab.width() {
+                    if let Some(cell) = stochastic.flip(&rule, self.cells_tab.get(x, y), &mut rng) {
+                        self.set_cell(x, y, cell);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn run(&mut self, generations: u64) {
+        for _ in 0..generations {
+            self.step();
+        }
+    }
+
+    // Avance de 2^k générations. Sur le plan infini avec une règle à deux
+    // états sans B0, de rayon 1, sans hasard, et le voisinage de Moore, le
+    // saut passe par HashLife ; sinon les générations sont calculées une à
+    // une. Le saut s'annule d'un coup ; une cellule pouvant y changer
+    // plusieurs fois, il est gardé par comparaison avec l'état d'avant.
+    pub fn jump(&mut self, k: u8) {
+        let noted = k == 0 && self.changes_noted();
+        self.record_with(noted, |simulation| {
+            if simulation.hashlife_enabled() {
+                simulation.jump_hashlife(k);
+            } else {
+                for _ in 0..1u64 << k {
+                    simulation.advance();
+                }
+            }
+        });
+    }
+
+    fn jump_hashlife(&mut self, k: u8) {
+        let Some(sparse) = &self.sparse else {
+            return;
+        };
+        let hashlife = match &mut self.hashlife {
+            Some(hashlife) if hashlife.rule() == self.rule => hashlife,
+            hashlife => hashlife.insert(HashLife::new(self.rule)),
+        };
+        let next = hashlife.jump(sparse, k);
+        self.number_of_living_cells = next.population();
+        self.sparse = Some(next);
+        self.refresh_view();
+        self.generation += 1 << k;
+        // Deux générations à 2^k d'écart ne disent rien de la période.
+        self.forget_cycles();
+    }
+
+    pub fn hashlife_enabled(&self) -> bool {
+        self.sparse.is_some()
+            && self.stochastic.is_deterministic()
+            && self.neighbourhood == Neighbourhood::Moore
+            && HashLife::supports(&self.rule)
+    }
+
+    // Nombre d'actions gardées pour être annulées ; 0 n'en garde aucune et
+    // épargne le relevé des cellules changées à chaque génération.
+    pub fn set_history_capacity(&mut self, capacity: usize) {
+        self.history = History::new(capacity);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    // Annule la dernière action : une génération, un saut ou une cellule
+    // modifiée à la main.
+    pub fn undo(&mut self) {
+        if let Some(change) = self.history.take_last() {
+            self.apply_change(&change, false);
+            self.history.undone(change);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(change) = self.history.take_next() {
+            self.apply_change(&change, true);
+            self.history.redone(change);
+        }
+    }
+
+    // Revient à la génération précédente, en annulant au passage les
+    // cellules modifiées à la main depuis.
+    pub fn step_back(&mut self) {
+        let generation = self.generation;
+        while self.generation == generation && self.can_undo() {
+            self.undo();
+        }
+    }
+
+    // Recense les objets de la génération, pour une règle à deux états.
+    pub fn census(&self) -> Result<Census, String> {
+        let rule = self.rule;
+        if rule.states() != 2 || rule.is_elementary() || rule.is_continuous() {
+            return Err("Le recensement demande une règle à deux états".to_string());
+        }
+        let mut next = self.clone();
+        next.set_history_capacity(0);
+        next.set_stochastic(Stochastic::default());
+        next.advance();
+        let (current, next) = (self.living_positions(), next.living_positions());
+        let census = if self.sparse.is_some() {
+            Census::take(
+                &current,
+                &next,
+                |(x, y), (dx, dy)| Some((x + dx, y + dy)),
+                &rule,
+                self.neighbourhood,
+            )
+        } else {
+            let (width, height) = (self.cells_tab.width(), self.cells_tab.height());
+            let topology = self.topology;
+            Census::take(
+                &current,
+                &next,
+                |(x, y), (dx, dy)| {
+                    topology
+                        .resolve((x + dx) as isize, (y + dy) as isize, width, height)
+                        .map(|(x, y)| (x as i64, y as i64))
+                },
+                &rule,
+                self.neighbourhood,
+            )
+        };
+        Ok(census)
+    }
+
+    fn living_positions(&self) -> Vec<(i64, i64)> {
+        match &self.sparse {
+            Some(sparse) => sparse
+                .cells()
+                .filter(|(_, cell)| self.rule.is_alive(cell.state))
+                .map(|(position, _)| position)
+                .collect(),
+            None => {
+                let mut positions = Vec::new();
+                for y in 0..self.cells_tab.height() {
+                    for (x, cell) in self.cells_tab.row(y).iter().enumerate() {
+                        if self.rule.is_alive(cell.state) {
+                            positions.push((x as i64, y as i64));
+                        }
+                    }
+                }
+                positions
+            }
+        }
+    }
+
+    // Naissances et morts de la dernière génération ou du dernier saut,
+    // connues tant que l'historique est gardé.
+    pub fn births_and_deaths(&self) -> Option<(u32, u32)> {
+        self.births_and_deaths
+    }
+
+    // Fait `action` en gardant dans l'historique ce qu'elle a changé. Quand
+    // chaque cellule changée est notée dans `journal`, seules celles-ci sont
+    // gardées ; sinon, l'état d'avant est comparé à celui d'après.
+    fn record(&mut self, action: impl FnOnce(&mut Self)) {
+        let noted = self.changes_noted();
+        self.record_with(noted, action);
+    }
+
+    fn record_with(&mut self, noted: bool, action: impl FnOnce(&mut Self)) {
+        if !self.history.is_enabled() {
+            action(self);
+            self.births_and_deaths = None;
+            return;
+        }
+        let generation = self.generation;
+        let row = self
+            .rule
+            .is_elementary()
+            .then(|| self.cells_tab.row(self.current_row()).to_vec());
+        let mut change = if noted {
+            self.journal = Some(Change::default());
+            action(self);
+            self.journal.take().unwrap_or_default()
+        } else {
+            self.compare(action)
+        };
+        change.generations = self.generation - generation;
+        if change.generations > 0 {
+            change.births_and_deaths = Some(self.count_births_and_deaths(&change, row.as_deref()));
+            self.births_and_deaths = change.births_and_deaths;
+        }
+        self.history.record(change);
+    }
+
+    // Vrai quand toute cellule que change une action est notée dans
+    // `journal`, une seule fois : sur une grille bornée à deux dimensions,
+    // sans valeurs continues ni hasard, quand la génération suivante est
+    // calculée par tuiles.
+    fn changes_noted(&self) -> bool {
+        self.sparse.is_none()
+            && !self.rule.is_continuous()
+            && !self.rule.is_elementary()
+            && !self.rule.is_larger_than_life()
+            && self.rule.colours() == 0
+            && self.stochastic.is_deterministic()
+    }
+
+    // Retire du hachage de la grille le bloc (x0, y) tel qu'il était, ou l'y
+    // ajoute tel qu'il est devenu.
+    fn toggle_block_hash(&mut self, x0: usize, y: usize) {
+        let hash = self.grid_block_hash(&self.cells_tab, x0, y);
+        if let Some(grid_hash) = &mut self.grid_hash {
+            *grid_hash ^= hash;
+        }
+    }
+
+    fn grid_block_hash(&self, grid: &Grid, x0: usize, y: usize) -> u64 {
+        let row = &grid.row(y)[x0..];
+        cells_block_hash(x0, y, &row[..row.len().min(64)])
+    }
+
+    fn note_change(&mut self, x: usize, y: usize, before: Cell, after: Cell) {
+        if let Some(journal) = &mut self.journal {
+            journal.cells.push(((x as i64, y as i64), before, after));
+        }
+    }
+
+    // Fait `action` et renvoie les cellules et les valeurs qui diffèrent
+    // entre l'état d'avant et celui d'après.
+    fn compare(&mut self, action: impl FnOnce(&mut Self)) -> Change {
+        if self.rule.is_continuous() && self.field.is_none() {
+            self.field = Some(Field::from_grid(&self.cells_tab));
+        }
+        let cells_tab = self.cells_tab.clone();
+        let sparse = self.sparse.clone();
+        let field = self.field.clone();
+        action(self);
+
+        let mut change = Change::default();
+        match (&sparse, &self.sparse) {
+            (Some(before), Some(after)) => {
+                for (position, cell) in after.cells() {
+                    let previous = before.get(position.0, position.1);
+                    if previous != cell {
+                        change.cells.push((position, previous, cell));
+                    }
+                }
+                for (position, cell) in before.cells() {
+                    if after.get(position.0, position.1) == Cell::DEAD {
+                        change.cells.push((position, cell, Cell::DEAD));
+                    }
+                }
+            }
+            _ => {
+                for y in 0..cells_tab.height() {
+                    if cells_tab.row(y) == self.cells_tab.row(y) {
+                        continue;
+                    }
+                    let rows = cells_tab.row(y).iter().zip(self.cells_tab.row(y));
+                    for (x, (&before, &after)) in rows.enumerate() {
+                        if before != after {
+                            change.cells.push(((x as i64, y as i64), before, after));
+                        }
+                    }
+                }
+            }
+        }
+        if let (Some(before), Some(after)) = (&field, &self.field) {
+            let values = before.values().iter().zip(after.values());
+            for (i, (&before, &after)) in values.enumerate() {
+                if before != after {
+                    change.values.push((i, before, after));
+                }
+            }
+        }
+        change
+    }
+
+    // Un automate à une dimension compare sa nouvelle ligne à celle de la
+    // génération de départ, `previous`.
+    fn count_births_and_deaths(&self, change: &Change, previous: Option<&[Cell]>) -> (u32, u32) {
+        let rule = self.rule;
+        let transitions: Vec<(Cell, Cell)> = if let Some(before) = previous {
+            let after = self.cells_tab.row(self.current_row());
+            before.iter().copied().zip(after.iter().copied()).collect()
+        } else {
+            change
+                .cells
+                .iter()
+                .map(|&(_, before, after)| (before, after))
+                .collect()
+        };
+        let (births, deaths) =
+            transitions
+                .iter()
+                .fold((0, 0), |(births, deaths), &(before, after)| {
+                    match (rule.is_alive(before.state), rule.is_alive(after.state)) {
+                        (false, true) => (births + 1, deaths),
+                        (true, false) => (births, deaths + 1),
+                        _ => (births, deaths),
+                    }
+                });
+        change
+            .words
+            .iter()
+            .fold((births, deaths), |(births, deaths), &(_, before, after)| {
+                (
+                    births + (!before & after).count_ones(),
+                    deaths + (before & !after).count_ones(),
+                )
+            })
+    }
+
+    // Remet les cellules et les valeurs dans leur état d'avant l'action
+    // (`forward` faux) ou d'après.
+    fn apply_change(&mut self, change: &Change, forward: bool) {
+        for &((x, y), before, after) in &change.cells {
+            let cell = if forward { after } else { before };
+            match &mut self.sparse {
+                Some(sparse) => sparse.set(x, y, cell),
+                None => self.cells_tab.set(x as usize, y as usize, cell),
+            }
+        }
+        let width = self.cells_tab.width();
+        for &((x0, y), before, after) in &change.words {
+            let word = if forward { after } else { before };
+            for x in x0..(x0 + 64).min(width) {
+                let state = (word >> (x - x0) & 1) as u8;
+                self.cells_tab.set(x, y, Cell { state });
+            }
+        }
+        if let Some(field) = &mut self.field {
+            for &(i, before, after) in &change.values {
+                field.values_mut()[i] = if forward { after } else { before };
+            }
+        }
+        if forward {
+            self.generation += change.generations;
+            if change.generations > 0 {
+                self.births_and_deaths = change.births_and_deaths;
+            }
+        } else {
+            self.generation -= change.generations;
+        }
+        self.refresh_view();
+        self.forget_caches();
+        self.forget_cycles();
+        self.number_of_living_cells = self.count_living_cells();
+    }
+
+    // Voisins vivants de (x, y) : le bit i est allumé si le voisin de
+    // décalage offsets[i] est vivant.
+    fn check_neighbours(&self, x: usize, y: usize, offsets: &[(isize, isize)]) -> u32 {
+        let mut configuration = 0;
+        let width = self.cells_tab.width();
+        let height = self.cells_tab.height();
+        for (i, (dx, dy)) in offsets.iter().enumerate() {
+            let neighbour = self
+                .topology
+                .resolve(x as isize + dx, y as isize + dy, width, height);
+
+            if let Some((nx, ny)) = neighbour {
+                if self.cells_tab.is_alive(nx, ny) {
+                    configuration |= 1 << i;
+                }
+            }
+        }
+        configuration
+    }
+
+    // Chaque génération d'un automate à une dimension est écrite sous la
+    // précédente ; une fois la grille pleine, le diagramme défile vers le
+    // haut.
+    fn step_elementary(&mut self) {
+        let Some(rule) = self.rule.elementary() else {
+            return;
+        };
+        let (width, height) = (self.cells_tab.width(), self.cells_tab.height());
+        let row = self.current_row();
+        let next = elementary::next_row(self.cells_tab.row(row), rule, self.topology);
+        let target = if row + 1 < height {
+            row + 1
+        } else {
+            self.cells_tab.cells_mut().rotate_left(width);
+            row
+        };
+        self.cells_tab.row_mut(target).copy_from_slice(&next);
+        self.number_of_living_cells = next.iter().filter(|cell| cell.is_alive()).count() as u32;
+        self.forget_caches();
+    }
+
+    // Les valeurs sont créées à partir des niveaux de la grille au premier
+    // pas, puis gardées d'une génération à l'autre.
+    fn step_continuous(&mut self) {
+        let field = self
+            .field
+            .get_or_insert_with(|| Field::from_grid(&self.cells_tab));
+        let next = self.lenia.step(field, self.topology, self.threads);
+        self.cells_tab = next.to_grid();
+        self.field = Some(next);
+        self.number_of_living_cells = self.count_living_cells();
+        self.forget_caches();
+    }
+
+    fn update_cells(&mut self) {
+        // Le voisinage d'une règle Larger than Life peut dépasser les tuiles
+        // voisines : toute la grille est recalculée.
+        if self.rule.is_larger_than_life() {
+            let (next, population) =
+                larger_than_life::step(&self.cells_tab, &self.rule, self.topology, self.threads);
+            self.cells_tab = next;
+            self.number_of_living_cells = population;
+            self.forget_caches();
+            return;
+        }
+
+        // Les naissances des variantes multicolores dépendent de la couleur
+        // des voisines, que le calcul par tuiles ne connaît pas.
+        if self.rule.colours() > 0 {
+            let (next, population) = multicolour::step(
+                &self.cells_tab,
+                &self.rule,
+                self.neighbourhood,
+                self.topology,
+                self.threads,
+            );
+            self.cells_tab = next;
+            self.number_of_living_cells = population;
+            self.forget_caches();
+            return;
+        }
+
+        // Les règles totalistiques à deux états passent par la grille de
+        // bits, bien plus rapide, gardée d'une génération à l'autre ; les
+        // cellules mourantes, les règles isotropes et les voisinages
+        // personnalisés imposent le calcul cellule par cellule.
+        if self.rule.states() == 2
+            && self.rule.is_totalistic()
+            && BitGrid::supports(self.neighbourhood)
+        {
+            if self.bits.is_none() {
+                self.bits = BitGrid::from_grid(&self.cells_tab);
+            }
+            if let Some(bits) = &self.bits {
+                let next = bits.step(&self.rule, self.topology, self.neighbourhood, self.threads);
+                next.write_changes_to(&mut self.cells_tab);
+                if self.journal.is_some() || self.grid_hash.is_some() {
+                    for ((x0, y), before, after) in next.changed_words() {
+                        if let Some(hash) = &mut self.grid_hash {
+                            *hash ^= block_hash(x0, y, &[before]) ^ block_hash(x0, y, &[after]);
+                        }
+                        if let Some(journal) = &mut self.journal {
+                            journal.words.push(((x0, y), before, after));
+                        }
+                    }
+                }
+                self.number_of_living_cells = next.population();
+                self.bits = Some(next);
+                self.changed_tiles = None;
+                return;
+            }
+        }
+        self.bits = None;
+
+        // Seules les tuiles voisines d'une tuile modifiée à la génération
+        // précédente peuvent changer.
+        let width = self.cells_tab.width();
+        let height = self.cells_tab.height();
+        let active = match &self.changed_tiles {
+            Some(changed_tiles) => changed_tiles.neighbourhood(),
+            None => TileMap::new(
+                width.div_ceil(Self::TILE_SIZE),
+                height.div_ceil(Self::TILE_SIZE),
+                true,
+            ),
+        };
+        let offsets = [
+            self.neighbourhood.offsets(false),
+            self.neighbourhood.offsets(true),
+        ];
+        let noted = self.journal.is_some();
+        let hashed = self.grid_hash.is_some();
+        let mut next_cells_tab = self.cells_tab.clone();
+        let bands = map_bands(
+            next_cells_tab.cells_mut(),
+            width,
+            self.threads,
+            |first_row, band| {
+                let mut population = 0;
+                let mut changed = Vec::new();
+                let mut cells = Vec::new();
+                let mut blocks: Vec<(usize, usize)> = Vec::new();
+                for (dy, row) in band.chunks_mut(width).enumerate() {
+                    let y = first_row + dy;
+                    for (x, next) in row.iter_mut().enumerate() {
+                        let tile = active.index(x / Self::TILE_SIZE, y / Self::TILE_SIZE);
+                        if active.get(tile) {
+                            let living = self.check_neighbours(x, y, &offsets[y % 2]);
+                            let state = self
+                                .neighbourhood
+                                .next_state(&self.rule, next.state, living);
+                            if state != next.state {
+                                if noted {
+                                    cells.push((x, y, *next, Cell { state }));
+                                }
+                                let block = (x - x % 64, y);
+                                if hashed && blocks.last() != Some(&block) {
+                                    blocks.push(block);
+                                }
+                                next.state = state;
+                                changed.push(tile);
+                            }
+                        }
+                        if next.is_alive() {
+                            population += 1;
+                        }
+                    }
+                }
+                (population, changed, cells, blocks)
+            },
+        );
+
+        let mut changed_tiles = TileMap::new(
+            width.div_ceil(Self::TILE_SIZE),
+            height.div_ceil(Self::TILE_SIZE),
+            false,
+        );
+        self.number_of_living_cells = 0;
+        for (population, changed, cells, blocks) in bands {
+            self.number_of_living_cells += population;
+            for tile in changed {
+                changed_tiles.mark(tile);
+            }
+            for (x, y, before, after) in cells {
+                self.note_change(x, y, before, after);
+            }
+            for (x0, y) in blocks {
+                let hash = self.grid_block_hash(&self.cells_tab, x0, y)
+                    ^ self.grid_block_hash(&next_cells_tab, x0, y);
+                if let Some(grid_hash) = &mut self.grid_hash {
+                    *grid_hash ^= hash;
+                }
+            }
+        }
+        self.changed_tiles = Some(changed_tiles);
+        self.cells_tab = next_cells_tab;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    // Tout ce qu'une annulation doit rendre. Les valeurs d'un automate
+    // continu sont créées au premier pas à partir de la grille.
+    type Snapshot = (Grid, Option<SparseGrid>, Option<Vec<f32>>, u64, u32);
+
+    fn snapshot(simulation: &Simulation) -> Snapshot {
+        let field = match &simulation.field {
+            Some(field) => Some(field.values().to_vec()),
+            None if simulation.rule.is_continuous() => {
+                Some(Field::from_grid(&simulation.cells_tab).values().to_vec())
+            }
+            None => None,
+        };
+        (
+            simulation.cells_tab.clone(),
+            simulation.sparse.clone(),
+            field,
+            simulation.generation,
+            simulation.population(),
+        )
+    }
+
+    fn random_simulation(
+        rule: Rule,
+        topology: Topology,
+        neighbourhood: Neighbourhood,
+    ) -> Simulation {
+        let mut rng = StdRng::seed_from_u64(3);
+        let grid = Grid::random_with_density(40, 24, 0.35, &mut rng);
+        let mut simulation = Simulation::with_rule(grid, rule);
+        simulation.set_neighbourhood(neighbourhood);
+        simulation.set_topology(topology);
+        simulation.set_seed(7);
+        // Un petit noyau suffit et garde les tests rapides.
+        simulation.set_lenia(Lenia {
+            radius: 4,
+            ..Lenia::default()
+        });
+        simulation
+    }
+
+    // Règles passant par chacun des calculs : grille de bits, tuiles,
+    // règles isotropes, Larger than Life, variantes multicolores, WireWorld,
+    // automates continus et à une dimension.
+    fn rules() -> Vec<Rule> {
+        [
+            "B3/S23",
+            "B36/S23",
+            "B2/S/C3",
+            "B3/S2-i34q",
+            "R2,C0,M1,S3..5,B3..4,NM",
+            "Immigration",
+            "QuadLife",
+            "WireWorld",
+            "Lenia",
+            "W110",
+        ]
+        .iter()
+        .map(|rule| rule.parse().unwrap())
+        .collect()
+    }
+
+    fn cases() -> Vec<(Rule, Topology, Neighbourhood)> {
+        let mut cases = Vec::new();
+        for rule in rules() {
+            for topology in Topology::ALL {
+                for neighbourhood in Neighbourhood::ALL {
+                    if topology.supports(&rule) && neighbourhood.supports(&rule) {
+                        cases.push((rule, topology, neighbourhood));
+                    }
+                }
+            }
+        }
+        cases
+    }
+
+    #[test]
+    fn undo_and_redo_retrace_the_game() {
+        let noisy = Stochastic {
+            birth: 0.9,
+            survival: 0.95,
+            noise: 0.01,
+        };
+        for (rule, topology, neighbourhood) in cases() {
+            // Le hasard ne touche ni les automates continus ni ceux à une
+            // dimension, et ne dépend pas du voisinage.
+            let random = neighbourhood == Neighbourhood::Moore
+                && !rule.is_continuous()
+                && !rule.is_elementary();
+            let stochastics = if random {
+                vec![Stochastic::default(), noisy]
+            } else {
+                vec![Stochastic::default()]
+            };
+            for stochastic in stochastics {
+                let mut simulation = random_simulation(rule, topology, neighbourhood);
+                simulation.set_stochastic(stochastic);
+                let mut rng = StdRng::seed_from_u64(5);
+                let start = snapshot(&simulation);
+                let mut forward = Vec::new();
+                for action in 0..30 {
+                    match action % 5 {
+                        1 | 3 => {
+                            let (x, y) = (rng.gen_range(0..40), rng.gen_range(0..24));
+                            simulation.toggle(x, y);
+                        }
+                        4 => simulation.jump(2),
+                        _ => simulation.step(),
+                    }
+                    forward.push(snapshot(&simulation));
+                }
+                let case = (rule.to_string(), topology, neighbourhood, stochastic);
+                for expected in forward.iter().rev().skip(1).chain([&start]) {
+                    simulation.undo();
+                    assert_eq!(&snapshot(&simulation), expected, "{:?}", case);
+                }
+                assert!(!simulation.can_undo(), "{:?}", case);
+                for expected in &forward {
+                    simulation.redo();
+                    assert_eq!(&snapshot(&simulation), expected, "{:?}", case);
+                }
+                assert!(!simulation.can_redo(), "{:?}", case);
+            }
+        }
+    }
+
+    #[test]
+    fn threads_do_not_change_the_result() {
+        for (rule, topology, neighbourhood) in cases() {
+            let mut single = random_simulation(rule, topology, neighbourhood);
+            single.set_threads(1);
+            let mut several = single.clone();
+            several.set_threads(4);
+            for generation in 0..12 {
+                single.step();
+                several.step();
+                let case = (rule.to_string(), topology, neighbourhood, generation);
+                assert_eq!(single.grid(), several.grid(), "{:?}", case);
+                assert_eq!(single.population(), several.population(), "{:?}", case);
+            }
+        }
+    }
+
+    // Une copie relue d'une sauvegarde n'a ni tuiles ni grille de bits :
+    // elle recalcule tout. Les cellules modifiées entre deux générations
+    // doivent l'être aussi dans les tuiles gardées.
+    #[test]
+    fn kept_tiles_match_a_fresh_copy() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for (rule, topology, neighbourhood) in cases() {
+            let mut simulation = random_simulation(rule, topology, neighbourhood);
+            let dying = Cell {
+                state: rule.states() - 1,
+            };
+            for action in 0..20 {
+                let (x, y) = (rng.gen_range(0..40), rng.gen_range(0..24));
+                match action % 5 {
+                    1 => simulation.toggle(x, y),
+                    2 => simulation.paint(x, y, dying),
+                    3 if action == 8 => {
+                        let cells: Vec<(i64, i64)> = (0..100)
+                            .map(|_| (rng.gen_range(0..40), rng.gen_range(0..24)))
+                            .collect();
+                        simulation.replace_cells(&cells);
+                    }
+                    _ => (),
+                }
+                let saved = serde_json::to_string(&simulation).unwrap();
+                let mut fresh: Simulation = serde_json::from_str(&saved).unwrap();
+                simulation.step();
+                fresh.step();
+                let case = (rule.to_string(), topology, neighbourhood, action);
+                assert_eq!(simulation.grid(), fresh.grid(), "{:?}", case);
+                assert_eq!(simulation.population(), fresh.population(), "{:?}", case);
+            }
+        }
+    }
+
+    #[test]
+    fn history_keeps_the_latest_actions() {
+        let mut simulation = random_simulation(Rule::CONWAY, Topology::Torus, Neighbourhood::Moore);
+        simulation.set_history_capacity(5);
+        let mut forward = Vec::new();
+        for _ in 0..10 {
+            simulation.step();
+            forward.push(snapshot(&simulation));
+        }
+        let mut undone = 0;
+        while simulation.can_undo() {
+            simulation.undo();
+            undone += 1;
+        }
+        assert_eq!(undone, 5);
+        assert_eq!(snapshot(&simulation), forward[4]);
+
+        simulation.set_history_capacity(0);
+        simulation.step();
+        assert!(!simulation.can_undo());
+    }
+}
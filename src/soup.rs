@@ -0,0 +1,57 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::census::Census;
+use crate::grid::Grid;
+use crate::rule::Rule;
+use crate::simulation::Simulation;
+
+// Soupes aléatoires : une grille width x height sur un tore, remplie avec la
+// densité `density` par un générateur tiré de la graine de la soupe, comme
+// le remplissage par densité de l'interface. Une graine trouvée ici redonne
+// donc la même soupe dans l'interface, avec les mêmes paramètres.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SoupSearch {
+    pub width: usize,
+    pub height: usize,
+    pub density: f64,
+    pub rule: Rule,
+    // Au-delà, une soupe qui ne se répète pas est recensée telle quelle.
+    pub max_generations: u64,
+}
+
+// Ce qu'est devenue une soupe : la génération où elle s'est stabilisée (ou
+// la dernière calculée) et ses objets à cette génération.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Soup {
+    pub seed: u64,
+    pub generation: u64,
+    pub settled: bool,
+    pub census: Census,
+}
+
+impl SoupSearch {
+    pub fn soup(&self, seed: u64) -> Simulation {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let grid = Grid::random_with_density(self.width, self.height, self.density, &mut rng);
+        let mut simulation = Simulation::with_rule(grid, self.rule);
+        simulation.set_seed(seed);
+        simulation
+    }
+
+    // Fait évoluer la soupe de graine `seed` jusqu'à ce qu'elle se répète.
+    pub fn run(&self, seed: u64) -> Result<Soup, String> {
+        let mut simulation = self.soup(seed);
+        simulation.set_threads(1);
+        simulation.set_history_capacity(0);
+        while simulation.cycle().is_none() && simulation.generation() < self.max_generations {
+            simulation.step();
+        }
+        Ok(Soup {
+            seed,
+            generation: simulation.generation(),
+            settled: simulation.cycle().is_some(),
+            census: simulation.census()?,
+        })
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::grid::{Cell, Grid};
+use crate::larger_than_life;
+use crate::multicolour;
+use crate::neighbourhood::{Mask, Neighbourhood};
+use crate::rule::Rule;
+use crate::topology::Topology;
+
+// Plan infini dont seules les cellules non mortes sont stockées.
+// Les règles avec B0 ne sont pas gérées : une cellule sans voisin vivant
+// n'est jamais examinée.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SparseGrid {
+    cells: HashMap<(i64, i64), Cell>,
+}
+
+impl SparseGrid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Recopie la grille en plaçant son coin supérieur gauche en (x0, y0).
+    pub fn from_grid(grid: &Grid, x0: i64, y0: i64) -> Self {
+        let mut sparse = SparseGrid::new();
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                sparse.set(x0 + x as i64, y0 + y as i64, grid.get(x, y));
+            }
+        }
+        sparse
+    }
+
+    pub fn get(&self, x: i64, y: i64) -> Cell {
+        self.cells.get(&(x, y)).copied().unwrap_or(Cell::DEAD)
+    }
+
+    pub fn set(&mut self, x: i64, y: i64, cell: Cell) {
+        if cell == Cell::DEAD {
+            self.cells.remove(&(x, y));
+        } else {
+            self.cells.insert((x, y), cell);
+        }
+    }
+
+    pub fn population(&self) -> u32 {
+        self.cells.values().filter(|cell| cell.is_alive()).count() as u32
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = ((i64, i64), Cell)> + '_ {
+        self.cells.iter().map(|(&position, &cell)| (position, cell))
+    }
+
+    // Rectangle (x_min, y_min, x_max, y_max) contenant toutes les cellules
+    // non mortes.
+    pub fn bounding_box(&self) -> Option<(i64, i64, i64, i64)> {
+        self.cells
+            .keys()
+            .fold(None, |bounds, &(x, y)| match bounds {
+                None => Some((x, y, x, y)),
+                Some((x_min, y_min, x_max, y_max)) => {
+                    Some((x_min.min(x), y_min.min(y), x_max.max(x), y_max.max(y)))
+                }
+            })
+    }
+
+    // Portion width x height du plan dont le coin supérieur gauche est (x0, y0).
+    pub fn window(&self, x0: i64, y0: i64, width: usize, height: usize) -> Grid {
+        let mut grid = Grid::new(width, height);
+        for (&(x, y), &cell) in &self.cells {
+            let (wx, wy) = (x - x0, y - y0);
+            if (0..width as i64).contains(&wx) && (0..height as i64).contains(&wy) {
+                grid.set(wx as usize, wy as usize, cell);
+            }
+        }
+        grid
+    }
+
+    pub fn step(&self, rule: &Rule, neighbourhood: Neighbourhood) -> SparseGrid {
+        if let Some(range) = rule.larger_than_life() {
+            return self.step_in_window(range.radius as i64, |window| {
+                larger_than_life::step(window, rule, Topology::Plane, 1).0
+            });
+        }
+        if rule.colours() > 0 {
+            return self.step_in_window(Mask::RADIUS as i64, |window| {
+                multicolour::step(window, rule, neighbourhood, Topology::Plane, 1).0
+            });
+        }
+
+        // Seules les cellules non mortes et leurs voisines peuvent changer.
+        // Chaque cellule vivante allume, chez chaque cellule dont elle est le
+        // voisin i, le bit i ; en hexagonal, les voisins d'une cellule
+        // dépendent de la parité de sa ligne.
+        let offsets = [neighbourhood.offsets(false), neighbourhood.offsets(true)];
+        let mut neighbours: HashMap<(i64, i64), u32> = HashMap::new();
+        for (&(x, y), cell) in &self.cells {
+            neighbours.entry((x, y)).or_insert(0);
+            if !cell.is_alive() {
+                continue;
+            }
+            for (parity, offsets) in offsets.iter().enumerate() {
+                for (i, &(dx, dy)) in offsets.iter().enumerate() {
+                    let (nx, ny) = (x - dx as i64, y - dy as i64);
+                    if ny.rem_euclid(2) as usize == parity {
+                        *neighbours.entry((nx, ny)).or_insert(0) |= 1 << i;
+                    }
+                }
+            }
+        }
+
+        let mut next = SparseGrid::new();
+        for ((x, y), living) in neighbours {
+            let state = neighbourhood.next_state(rule, self.get(x, y).state, living);
+            next.set(x, y, Cell { state });
+        }
+        next
+    }
+
+    // Hors du rectangle des cellules non mortes élargi du rayon du voisinage,
+    // rien ne peut naître : la génération est calculée sur ce rectangle
+    // comme sur un plan borné.
+    fn step_in_window(&self, radius: i64, step: impl Fn(&Grid) -> Grid) -> SparseGrid {
+        let Some((x_min, y_min, x_max, y_max)) = self.bounding_box() else {
+            return SparseGrid::new();
+        };
+        let (x0, y0) = (x_min - radius, y_min - radius);
+        let window = self.window(
+            x0,
+            y0,
+            (x_max - x_min + 2 * radius + 1) as usize,
+            (y_max - y_min + 2 * radius + 1) as usize,
+        );
+        SparseGrid::from_grid(&step(&window), x0, y0)
+    }
+}
+
+// Sauvegardé sous la forme d'une liste triée de [x, y, état].
+impl Serialize for SparseGrid {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut cells: Vec<(i64, i64, u8)> = self
+            .cells
+            .iter()
+            .map(|(&(x, y), cell)| (x, y, cell.state))
+            .collect();
+        cells.sort_unstable();
+        cells.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SparseGrid {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let cells: Vec<(i64, i64, u8)> = Vec::deserialize(deserializer)?;
+        let mut sparse = SparseGrid::new();
+        for (x, y, state) in cells {
+            sparse.set(x, y, Cell { state });
+        }
+        Ok(sparse)
+    }
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::grid::Cell;
+use crate::rule::Rule;
+
+// Hasard ajouté à la règle : une naissance prévue par la règle n'a lieu
+// qu'avec la probabilité `birth`, une survie avec la probabilité `survival`,
+// puis chaque cellule est retournée (vivante ou morte) avec la probabilité
+// `noise`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Stochastic {
+    pub birth: f64,
+    pub survival: f64,
+    pub noise: f64,
+}
+
+impl Default for Stochastic {
+    fn default() -> Self {
+        Stochastic {
+            birth: 1.0,
+            survival: 1.0,
+            noise: 0.0,
+        }
+    }
+}
+
+impl Stochastic {
+    pub fn is_valid(&self) -> bool {
+        [self.birth, self.survival, self.noise]
+            .iter()
+            .all(|p| (0.0..=1.0).contains(p))
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        *self == Stochastic::default()
+    }
+
+    // Générateur d'une génération : il ne dépend que de la graine et du
+    // numéro de génération, pour qu'une partie se rejoue à l'identique quel
+    // que soit le nombre de fils ou le point de départ.
+    pub(crate) fn rng(seed: u64, generation: u64) -> StdRng {
+        StdRng::seed_from_u64(seed ^ generation.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+    }
+
+    // Etat d'une cellule passée de `previous` à `next` selon la règle : une
+    // naissance annulée laisse la cellule morte, une survie annulée la fait
+    // mourir comme si la règle l'avait voulu.
+    pub(crate) fn apply(&self, rule: &Rule, previous: Cell, next: Cell, rng: &mut StdRng) -> Cell {
+        let born = !rule.is_alive(previous.state) && rule.is_alive(next.state);
+        let survives = rule.is_alive(previous.state) && previous == next;
+        if born && !rng.gen_bool(self.birth) {
+            previous
+        } else if survives && !rng.gen_bool(self.survival) {
+            // Les règles Generations font passer par l'état 2.
+            let generations = rule.states() > 2 && rule.colours() == 0;
+            Cell {
+                state: if generations { 2 } else { 0 },
+            }
+        } else {
+            next
+        }
+    }
+
+    // Retourne la cellule avec la probabilité `noise` ; une cellule qui
+    // s'allume dans une variante multicolore prend une couleur au hasard.
+    pub(crate) fn flip(&self, rule: &Rule, cell: Cell, rng: &mut StdRng) -> Option<Cell> {
+        if !rng.gen_bool(self.noise) {
+            return None;
+        }
+        let state = match rule.colours() {
+            _ if rule.is_alive(cell.state) => 0,
+            0 => 1,
+            colours => rng.gen_range(1..=colours),
+        };
+        Some(Cell { state })
+    }
+}
+
+// En pourcentages.
+impl fmt::Display for Stochastic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "naissance {} %, survie {} %, bruit {} %",
+            self.birth * 100.0,
+            self.survival * 100.0,
+            self.noise * 100.0
+        )
+    }
+}
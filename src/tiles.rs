@@ -0,0 +1,66 @@
+// Découpage d'une grille en tuiles, avec un drapeau par tuile (par exemple
+// "modifiée à la dernière génération").
+#[derive(Debug, Clone)]
+pub(crate) struct TileMap {
+    columns: usize,
+    rows: usize,
+    flags: Vec<bool>,
+}
+
+impl TileMap {
+    pub(crate) fn new(columns: usize, rows: usize, flag: bool) -> TileMap {
+        TileMap {
+            columns,
+            rows,
+            flags: vec![flag; columns * rows],
+        }
+    }
+
+    pub(crate) fn index(&self, column: usize, row: usize) -> usize {
+        row * self.columns + column
+    }
+
+    pub(crate) fn get(&self, tile: usize) -> bool {
+        self.flags[tile]
+    }
+
+    pub(crate) fn mark(&mut self, tile: usize) {
+        self.flags[tile] = true;
+    }
+
+    pub(crate) fn marked(&self) -> impl Iterator<Item = usize> + '_ {
+        self.flags
+            .iter()
+            .enumerate()
+            .filter(|(_, &flag)| flag)
+            .map(|(tile, _)| tile)
+    }
+
+    fn on_border(&self, tile: usize) -> bool {
+        let (column, row) = (tile % self.columns, tile / self.columns);
+        row == 0 || row == self.rows - 1 || column == 0 || column == self.columns - 1
+    }
+
+    // Tuiles qui touchent une tuile marquée. Les bords pouvant être raccordés
+    // entre eux par la topologie, une tuile marquée sur un bord fait marquer
+    // tous les bords.
+    pub(crate) fn neighbourhood(&self) -> TileMap {
+        let mut neighbourhood = TileMap::new(self.columns, self.rows, false);
+        let mut border_marked = false;
+        for tile in self.marked() {
+            let (column, row) = (tile % self.columns, tile / self.columns);
+            border_marked |= self.on_border(tile);
+            for r in row.saturating_sub(1)..=(row + 1).min(self.rows - 1) {
+                for c in column.saturating_sub(1)..=(column + 1).min(self.columns - 1) {
+                    neighbourhood.flags[r * self.columns + c] = true;
+                }
+            }
+        }
+        if border_marked {
+            for tile in 0..self.flags.len() {
+                neighbourhood.flags[tile] |= self.on_border(tile);
+            }
+        }
+        neighbourhood
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rule::Rule;
+
+// Façon dont les bords de la grille sont raccordés entre eux.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Topology {
+    // Les bords opposés sont raccordés : ce qui sort à droite rentre à gauche.
+    #[default]
+    Torus,
+    // Tout ce qui est hors de la grille est mort.
+    Plane,
+    // Raccordement normal à gauche et à droite, retourné en haut et en bas.
+    KleinBottle,
+    // Raccordement retourné sur les deux paires de bords (plan projectif).
+    CrossSurface,
+    // Raccordement à gauche et à droite seulement, mort en haut et en bas.
+    Cylinder,
+    // Chaque bord renvoie l'image miroir des cellules qui le bordent.
+    Mirror,
+    // Plan infini : la grille n'est que la partie visible du plan.
+    Unbounded,
+}
+
+impl Topology {
+    pub const ALL: [Topology; 7] = [
+        Topology::Torus,
+        Topology::Plane,
+        Topology::KleinBottle,
+        Topology::CrossSurface,
+        Topology::Cylinder,
+        Topology::Mirror,
+        Topology::Unbounded,
+    ];
+
+    // Le diagramme espace-temps d'un automate à une dimension et les valeurs
+    // d'un automate continu ne tiennent que dans une grille bornée. Avec B0,
+    // tout le plan infini naîtrait d'un coup.
+    pub fn supports(&self, rule: &Rule) -> bool {
+        *self != Topology::Unbounded
+            || !(rule.is_elementary() || rule.is_continuous() || rule.next_state_for(0, 0) != 0)
+    }
+
+    // Ramène les coordonnées (x, y), éventuellement hors de la grille, à une
+    // cellule de la grille. Renvoie None si la cellule est hors du monde.
+    pub fn resolve(
+        &self,
+        x: isize,
+        y: isize,
+        width: usize,
+        height: usize,
+    ) -> Option<(usize, usize)> {
+        let (w, h) = (width as isize, height as isize);
+        let inside = (0..w).contains(&x) && (0..h).contains(&y);
+        match self {
+            _ if inside => Some((x as usize, y as usize)),
+            Topology::Torus => Some((x.rem_euclid(w) as usize, y.rem_euclid(h) as usize)),
+            Topology::Plane | Topology::Unbounded => None,
+            Topology::Cylinder if (0..h).contains(&y) => {
+                Some((x.rem_euclid(w) as usize, y as usize))
+            }
+            Topology::Cylinder => None,
+            Topology::KleinBottle | Topology::CrossSurface => {
+                let (mut nx, mut ny) = (x.rem_euclid(w), y.rem_euclid(h));
+                // Chaque passage par un bord retourné inverse l'autre coordonnée.
+                if y.div_euclid(h) % 2 != 0 {
+                    nx = w - 1 - nx;
+                }
+                if *self == Topology::CrossSurface && x.div_euclid(w) % 2 != 0 {
+                    ny = h - 1 - ny;
+                }
+                Some((nx as usize, ny as usize))
+            }
+            Topology::Mirror => {
+                let reflect = |v: isize, size: isize| {
+                    let v = v.rem_euclid(2 * size);
+                    if v >= size {
+                        2 * size - 1 - v
+                    } else {
+                        v
+                    }
+                };
+                Some((reflect(x, w) as usize, reflect(y, h) as usize))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Topology::Torus => "Tore",
+            Topology::Plane => "Plan borné",
+            Topology::KleinBottle => "Bouteille de Klein",
+            Topology::CrossSurface => "Surface croisée",
+            Topology::Cylinder => "Cylindre",
+            Topology::Mirror => "Bords miroirs",
+            Topology::Unbounded => "Plan infini",
+        };
+        write!(f, "{}", name)
+    }
+}